@@ -1,9 +1,15 @@
+use chrono::{DateTime, Local};
 use gapbuf::GapBuffer;
 use ratatui::style::Color;
+use ratatui::layout::{Position, Rect};
 use std::collections::HashMap;
-use wl_clipboard_rs::copy::{MimeType, Options, Source};
-use crate::irc::IrcCommand;
-use crate::servers::ServerConfig;
+use std::time::{Duration, Instant};
+use crate::irc::{IrcCommand, ChannelListEntry, ConnectionState, ISupport, MessageTags, PresenceKind, is_valid_channel_name, is_valid_nick};
+use crate::servers::{Server, ServerConfig};
+use crate::config::{AppConfig, StartMode, WrapMode};
+use crate::theme::Theme;
+use crate::logging::{self, ChatLog};
+use crate::clipboard;
 use crate::ui::color_for_user;
 use crate::irc::{get_config_dir, create_default_servers_config};
 
@@ -11,14 +17,132 @@ use crate::irc::{get_config_dir, create_default_servers_config};
 pub enum ServerTreeItem {
     Server { server_idx: usize },
     Channel { server_idx: usize, channel_idx: usize },
+    DmHeader { server_idx: usize }, // "Direct Messages", the server's DMs follow it
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct ChannelMessages {
     pub messages: Vec<ColoredMessage>,
     pub msg_index: usize,
     pub msg_scroll: usize,
     pub viewport_height: usize,
+    pub draft: String,
+    pub draft_cursor: usize,
+    // follow new messages, cleared when the user scrolls away from the bottom
+    pub stick_to_bottom: bool,
+    // length at the last view clear, following the bottom never scrolls above it
+    pub clear_mark: usize,
+    // set once the on-disk log was read into the front of the buffer
+    pub history_loaded: bool,
+    // messages.len() when we last switched away, the "new messages" line goes in front of the next one
+    pub last_read_index: Option<usize>,
+}
+
+impl Default for ChannelMessages {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+            msg_index: 0,
+            msg_scroll: 0,
+            viewport_height: 0,
+            draft: String::new(),
+            draft_cursor: 0,
+            stick_to_bottom: true,
+            clear_mark: 0,
+            history_loaded: false,
+            last_read_index: None,
+        }
+    }
+}
+
+impl ChannelMessages {
+    // Append a message, only moving the view if we are following the bottom
+    pub fn push(&mut self, msg: ColoredMessage) {
+        self.messages.push(msg);
+
+        if self.stick_to_bottom {
+            self.msg_index = self.messages.len() - 1;
+            if self.viewport_height > 0 {
+                self.msg_scroll = self.bottom_scroll();
+            }
+        }
+    }
+
+    // Put older messages in front of the buffer without moving what the user is looking at
+    pub fn prepend_history(&mut self, history: Vec<ColoredMessage>) {
+        let added = history.len();
+        if added == 0 {
+            return;
+        }
+        self.messages.splice(0..0, history);
+        if self.clear_mark > 0 {
+            self.clear_mark += added;
+        }
+        if let Some(last_read) = &mut self.last_read_index {
+            *last_read += added;
+        }
+
+        if self.stick_to_bottom {
+            self.msg_index = self.messages.len() - 1;
+            if self.viewport_height > 0 {
+                self.msg_scroll = self.bottom_scroll();
+            }
+        } else {
+            self.msg_index += added;
+            self.msg_scroll += added;
+        }
+    }
+
+    // Scroll offset that shows the newest messages
+    pub fn bottom_scroll(&self) -> usize {
+        self.messages
+            .len()
+            .saturating_sub(self.viewport_height)
+            .max(self.clear_mark.min(self.messages.len()))
+    }
+
+    // Scroll everything out of view but keep it, scrolling up brings it back
+    pub fn clear_view(&mut self) {
+        self.clear_mark = self.messages.len();
+        self.msg_scroll = self.clear_mark;
+        self.msg_index = self.messages.len().saturating_sub(1);
+        self.stick_to_bottom = true;
+    }
+
+    // Drop the scrollback and free its memory
+    pub fn clear_buffer(&mut self) {
+        self.messages = Vec::new();
+        self.msg_index = 0;
+        self.msg_scroll = 0;
+        self.clear_mark = 0;
+        self.last_read_index = None;
+        self.stick_to_bottom = true;
+    }
+
+    // Join/part/quit lines, folded into the previous one when it is also presence
+    pub fn push_presence(&mut self, kind: PresenceKind, text: String, time: DateTime<Local>, collapse: bool) {
+        if collapse && let Some(last) = self.messages.last_mut() && !last.presence.is_empty() {
+            last.presence.push((kind, text.clone()));
+            last.text = last.presence_summary();
+            return;
+        }
+
+        self.push(ColoredMessage {
+            nick: None,
+            text: text.clone(),
+            color: None,
+            time,
+            presence: vec![(kind, text)],
+            banner: false,
+            historical: false,
+            msgid: None,
+            reply_to: None,
+        });
+    }
+
+    fn update_stick_to_bottom(&mut self) {
+        self.stick_to_bottom = self.msg_index + 1 >= self.messages.len();
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -27,18 +151,37 @@ pub struct ChannelContext {
     pub channel_name: String,
 }
 
+// Nicks offered for the word being typed, shown while it fits more than one
+#[derive(Debug, Clone)]
+pub struct NickPopup {
+    pub start: usize, // where the word being completed begins
+    pub matches: Vec<String>,
+    pub index: usize,
+    // Tab picked it up. Until then it only lists the matches and every key goes on to the mode
+    pub active: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClientInfo {
     pub name: String,
+    pub prefix: Option<char>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ChannelInfo {
     pub name: String,
     pub topic: Option<String>,
+    // from RPL_TOPICWHOTIME or the TOPIC change we saw
+    pub topic_set_by: Option<String>,
+    pub topic_set_at: Option<DateTime<Local>>,
     pub client_count: Option<usize>,
     pub is_joined: bool,
     pub is_dm: bool,
+    pub highlighted: bool,
+    // chat messages that arrived while another channel was shown
+    pub unread_count: usize,
+    // +k key we last joined with, cycle and rejoins send it again
+    pub key: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +190,34 @@ pub struct ServerInfo {
     pub is_connected: bool,
     pub channels: Vec<ChannelInfo>,
     pub is_expanded: bool,
+    pub autoconnect: bool,
+}
+
+impl ServerInfo {
+    // A servers.toml entry, its saved channels listed but not joined yet
+    pub fn from_saved(server: &Server) -> Self {
+        let mut info = Self {
+            name: server.name.clone(),
+            is_connected: false,
+            channels: Vec::new(),
+            is_expanded: !server.channels.is_empty(),
+            autoconnect: server.autoconnect,
+        };
+        info.add_saved_channels(server);
+        info
+    }
+
+    pub fn add_saved_channels(&mut self, server: &Server) {
+        for name in &server.channels {
+            if !self.channels.iter().any(|c| c.name.eq_ignore_ascii_case(name)) {
+                self.channels.push(ChannelInfo {
+                    name: name.clone(),
+                    is_joined: false,
+                    ..Default::default()
+                });
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +225,60 @@ pub struct ColoredMessage {
     pub nick: Option<String>,
     pub text: String,
     pub color: Option<Color>,
+    pub time: DateTime<Local>,
+    // join/part/quit lines folded into this message, empty for everything else
+    pub presence: Vec<(PresenceKind, String)>,
+    // the welcome art, drawn as is without the system message prefix
+    pub banner: bool,
+    // read back from the log file, drawn dimmed
+    pub historical: bool,
+    // IRCv3 msgid tag, what redactions (and later replies or reactions) point at
+    pub msgid: Option<String>,
+    // +draft/reply tag, the msgid this message answers
+    pub reply_to: Option<String>,
+}
+
+impl ColoredMessage {
+    // "[13:37] <nick> text", system lines have no nick
+    pub fn quoted_line(&self) -> String {
+        let time = self.time.format(QUOTE_TIME_FORMAT);
+        match &self.nick {
+            Some(nick) => format!("[{}] <{}> {}", time, nick, self.text),
+            None => format!("[{}] {}", time, self.text),
+        }
+    }
+
+    // "12 joins, 3 parts" for a folded run of join/part/quit lines
+    pub fn presence_summary(&self) -> String {
+        let count = |kind| self.presence.iter().filter(|(k, _)| *k == kind).count();
+        [
+            (count(PresenceKind::Join), "join", "joins"),
+            (count(PresenceKind::Part), "part", "parts"),
+            (count(PresenceKind::Quit), "quit", "quits"),
+        ]
+        .iter()
+        .filter(|(n, _, _)| *n > 0)
+        .map(|(n, one, many)| format!("{} {}", n, if *n == 1 { one } else { many }))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UrlSpan {
+    pub y: u16,
+    pub x_start: u16,
+    pub x_end: u16,
+    pub url: String,
+}
+
+// Where a channel's tab was drawn, for clicks on the tab bar
+#[derive(Debug, Clone)]
+pub struct TabSpan {
+    pub y: u16,
+    pub x_start: u16,
+    pub x_end: u16,
+    pub ctx: ChannelContext,
 }
 
 #[derive(Default, Debug, PartialEq, Clone)]
@@ -66,9 +291,58 @@ pub enum VimMode {
     Messages,
     Clients,
     Vimless,
+    Help,
+    ChannelList,
+}
+
+// A Normal mode edit of the input that . can repeat
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    DeleteLine,                          // dd
+    DeleteInnerWord,                     // diw
+    Put,                                 // p, pastes whatever is yanked at the time
+    Replace { c: char, count: usize },   // r<c>, 3r<c>
 }
 
-#[derive(Default)]
+// (server, channel) key of the buffer shown while no channel is selected,
+// so messages from before the first connect aren't lost
+const SYSTEM_BUFFER: (&str, &str) = ("", "system");
+
+fn system_buffer_key() -> (String, String) {
+    (SYSTEM_BUFFER.0.to_string(), SYSTEM_BUFFER.1.to_string())
+}
+
+// Upper bound on /list entries kept in memory
+const MAX_CHANNEL_LIST: usize = 10_000;
+
+// Sent with AWAY when auto_away_minutes runs out
+const AUTO_AWAY_MESSAGE: &str = "Away from keyboard";
+
+// How long a locally echoed message waits for a copy from the server to swallow
+const SELF_ECHO_WINDOW: Duration = Duration::from_secs(10);
+
+// Timestamp on messages copied with Y
+const QUOTE_TIME_FORMAT: &str = "%H:%M";
+
+// When a topic was set, in the topic command and on join
+const TOPIC_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+// The input box grows with multi-line input up to this many rows, then scrolls
+const MAX_INPUT_ROWS: usize = 5;
+
+// Shown instead of a message its sender (or an op) deleted
+const REDACTED_TEXT: &str = "(message deleted)";
+
+// How long the borders stay red after an error
+const ERROR_FLASH: Duration = Duration::from_millis(300);
+
+// Sent lines kept for Up/Down in Vimless mode
+const INPUT_HISTORY_LEN: usize = 100;
+
+// Columns added or taken per < / > press, and the narrowest a side panel may get
+const PANEL_RESIZE_STEP: u16 = 2;
+const MIN_PANEL_WIDTH: u16 = 5;
+
 pub struct App {
     pub msg: GapBuffer<char>,
     pub cmd: GapBuffer<char>,
@@ -77,22 +351,91 @@ pub struct App {
     pub messages_cmd: String,
     pub clients_cmd: String,
     pub sel_start: Option<usize>,
+    // anchor of the V line selection in Messages mode, the other end is msg_index
+    pub msg_sel_start: Option<usize>,
     pub yank: String,
     pub msg_cursor: usize,
     pub cmd_cursor: usize,
     pub channel: String,
     pub should_quit: bool,
+    // quitting was asked for with text still in the input, the next key answers y/n
+    pub quit_confirm: bool,
+    // Insert mode entered with R, typed characters replace the ones under the cursor
+    pub overwrite: bool,
+    // what . repeats in Normal mode
+    pub last_change: Option<Change>,
+    pub nick_popup: Option<NickPopup>,
+    // Vimless Up/Down: what was sent, where we are in it, and the unsent text to come back to
+    pub input_history: Vec<String>,
+    pub input_history_index: Option<usize>,
+    pub input_history_draft: String,
     pub vim_mode: VimMode,
     pub is_connected: bool,
+    pub connection_state: ConnectionState,
+    // IRCv3 caps the server granted on the current connection
+    pub enabled_caps: Vec<String>,
+    pub isupport: ISupport,
+    // (channel, text, when) of messages we echoed ourselves, see is_self_echo
+    pub recent_sent: Vec<(String, String, Instant)>,
+    pub last_activity: Instant,
+    // when the last IRC error arrived, the borders flash red for a moment after it
+    pub error_flash: Option<Instant>,
+    // a failed system copy was reported, later ones stay quiet
+    pub clipboard_warned: bool,
+    // a failed log write was reported, quiet until writing works again
+    pub log_failed: bool,
+    // lines waiting to be written when logging is on
+    pub chat_log: ChatLog,
+    // set while we are away because of auto_away_minutes, not a manual AWAY
+    pub auto_away: bool,
+    pub identified: bool,
     pub servers: Vec<ServerInfo>,
     pub server_tree: Vec<ServerTreeItem>,
     pub server_tree_index: usize,
+    // Some while "/" filtering is on in Server mode, narrows the tree without touching servers
+    pub server_filter: Option<String>,
     pub prev_mode: Option<VimMode>,
     pub client_index: usize,
     pub clients: Vec<ClientInfo>,
+    // F3 / F4 keep the server tree / clients panel open in every mode, not just their own
+    pub pin_servers: bool,
+    pub pin_clients: bool,
+    // Some while "/" filtering is on in Clients mode
+    pub client_filter: Option<String>,
+    // indices into clients that pass the filter, client_index points into this
+    pub client_view: Vec<usize>,
     pub current_nick: String,
     pub current_channel: Option<ChannelContext>,
     pub channel_messages: HashMap<(String,String), ChannelMessages>,
+    pub last_server: Option<String>,
+    pub msg_area: Rect,
+    // where the Command popup was last drawn, for mouse hit-testing
+    pub cmd_popup_area: Rect,
+    pub msg_rows: Vec<String>,
+    pub mouse_sel: Option<((u16, u16), (u16, u16))>,
+    pub msg_urls: Vec<UrlSpan>,
+    pub channel_tabs: Vec<TabSpan>,
+    pub help_scroll: usize,
+    pub msg_hscroll: usize,
+    // The input the "press Enter again" prompt was shown for, any edit since asks again
+    pub paste_confirm: Option<String>,
+    pub config: AppConfig,
+    pub theme: Theme,
+    pub channel_list: Vec<ChannelListEntry>,
+    pub channel_list_filter: String,
+    pub channel_list_index: usize,
+    pub channel_list_scroll: usize,
+    pub channel_list_done: bool,
+    // --demo: nothing from the made-up network is written to the logs, whatever config.toml says
+    pub demo: bool,
+    // Keys sent with a JOIN the server hasn't answered yet, by (server, lowercased channel)
+    pub pending_join_keys: HashMap<(String, String), String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -106,13 +449,24 @@ impl App {
             .unwrap_or_else(|_| ServerConfig::default_config());
         let servers = server_config.servers
             .iter()
-            .map(|s| ServerInfo {
-                name: s.name.clone(),
-                is_connected: false,
-                channels: Vec::new(),
-                is_expanded: false,
-            })
+            .map(ServerInfo::from_saved)
             .collect();
+        let config = AppConfig::load(config_dir.join("config.toml").to_str().expect("Invalid path"))
+            .unwrap_or_default();
+        let theme = Theme::load(config_dir.join("theme.toml").to_str().expect("Invalid path"))
+            .unwrap_or_default();
+        Self::with_config(config, theme, servers)
+    }
+
+    // Everything App::new reads from disk passed in instead
+    pub fn with_config(config: AppConfig, theme: Theme, servers: Vec<ServerInfo>) -> Self {
+        let vim_mode = match config.ui.default_mode {
+            StartMode::Normal => VimMode::Normal,
+            StartMode::Insert => VimMode::Insert,
+            StartMode::Vimless => VimMode::Vimless,
+        };
+        // Same as the vimless command leaves it
+        let prev_mode = (vim_mode == VimMode::Vimless).then_some(VimMode::Vimless);
         Self {
             msg: GapBuffer::new(),
             cmd: GapBuffer::new(),
@@ -124,25 +478,78 @@ impl App {
             cmd_cursor: 0,
             channel: String::new(),
             should_quit: false,
-            vim_mode: VimMode::Normal,
+            quit_confirm: false,
+            overwrite: false,
+            last_change: None,
+            nick_popup: None,
+            input_history: Vec::new(),
+            input_history_index: None,
+            input_history_draft: String::new(),
+            vim_mode,
             sel_start: None,
+            msg_sel_start: None,
             yank: String::new(),
             is_connected: false,
+            connection_state: ConnectionState::Disconnected,
+            enabled_caps: Vec::new(),
+            isupport: ISupport::default(),
+            recent_sent: Vec::new(),
+            last_activity: Instant::now(),
+            error_flash: None,
+            clipboard_warned: false,
+            log_failed: false,
+            chat_log: ChatLog::default(),
+            auto_away: false,
+            identified: false,
             servers,
             server_tree: Vec::new(),
             server_tree_index: 0,
-            prev_mode: None,
+            server_filter: None,
+            prev_mode,
             client_index: 0,
             clients: Vec::new(),
+            pin_servers: false,
+            pin_clients: false,
+            client_filter: None,
+            client_view: Vec::new(),
             current_nick: String::new(),
             channel_messages: HashMap::new(),
             current_channel: None,
+            last_server: None,
+            msg_area: Rect::default(),
+            cmd_popup_area: Rect::default(),
+            msg_rows: Vec::new(),
+            mouse_sel: None,
+            msg_urls: Vec::new(),
+            channel_tabs: Vec::new(),
+            help_scroll: 0,
+            msg_hscroll: 0,
+            paste_confirm: None,
+            config,
+            theme,
+            channel_list: Vec::new(),
+            channel_list_filter: String::new(),
+            channel_list_index: 0,
+            channel_list_scroll: 0,
+            channel_list_done: false,
+            demo: false,
+            pending_join_keys: HashMap::new(),
         }
     }
 
+    // For tests: an App that never touches ~/.config, with text in the input and the cursor at cursor
+    #[cfg(test)]
+    pub(crate) fn for_test(text: &str, cursor: usize) -> Self {
+        let mut app = Self::with_config(AppConfig::default(), Theme::default(), Vec::new());
+        app.insert_msg_str(text);
+        app.msg_cursor = cursor;
+        app
+    }
+
     pub fn get_mode_name(&self) -> &str {
         match self.vim_mode {
             VimMode::Normal => "NORMAL",
+            VimMode::Insert if self.overwrite => "REPLACE",
             VimMode::Insert => "INSERT",
             VimMode::Visual => "VISUAL",
             VimMode::Command => "COMMAND",
@@ -150,41 +557,323 @@ impl App {
             VimMode::Messages => "MESSAGES",
             VimMode::Clients => "CLIENTS",
             VimMode::Vimless => "VIMLESS",
+            VimMode::Help => "HELP",
+            VimMode::ChannelList => "LIST",
+        }
+    }
+
+    pub fn save_config(&mut self) {
+        let path = get_config_dir().join("config.toml");
+        if let Err(e) = self.config.save(path.to_str().expect("Invalid path")) {
+            self.push_system_to_current(format!("✖ Failed to save config: {}", e));
+        }
+    }
+
+    // Re-read config.toml and servers.toml, keeping the live state of servers that are still listed
+    pub fn reload_config(&mut self) {
+        let config_dir = get_config_dir();
+        match AppConfig::load(config_dir.join("config.toml").to_str().expect("Invalid path")) {
+            Ok(config) => self.config = config,
+            Err(e) => self.push_system_to_current(format!("✖ Failed to reload config.toml: {}", e)),
+        }
+        match Theme::load(config_dir.join("theme.toml").to_str().expect("Invalid path")) {
+            Ok(theme) => self.theme = theme,
+            Err(e) => self.push_system_to_current(format!("✖ Failed to reload theme.toml: {}", e)),
+        }
+
+        let server_config = match ServerConfig::load(config_dir.join("servers.toml").to_str().expect("Invalid path")) {
+            Ok(config) => config,
+            Err(e) => {
+                self.push_system_to_current(format!("✖ Failed to reload servers.toml: {}", e));
+                return;
+            }
+        };
+
+        let mut old_servers = std::mem::take(&mut self.servers);
+        for server in &server_config.servers {
+            let info = match old_servers.iter().position(|s| s.name == server.name) {
+                Some(pos) => {
+                    let mut info = old_servers.remove(pos);
+                    info.add_saved_channels(server);
+                    info.autoconnect = server.autoconnect;
+                    info
+                }
+                None => ServerInfo::from_saved(server),
+            };
+            self.servers.push(info);
+        }
+        // Don't drop a live connection just because it was removed from the file
+        self.servers.extend(old_servers.into_iter().filter(|s| s.is_connected));
+
+        self.rebuild_server_tree();
+    }
+
+    // edit_server renamed a saved server, its tree entry and buffers go with it
+    pub fn rename_server(&mut self, old: &str, new: &str) {
+        for server in self.servers.iter_mut().filter(|s| s.name == old) {
+            server.name = new.to_string();
+        }
+        let keys: Vec<(String, String)> = self.channel_messages.keys().filter(|(s, _)| s == old).cloned().collect();
+        for key in keys {
+            if let Some(msgs) = self.channel_messages.remove(&key) {
+                self.channel_messages.insert((new.to_string(), key.1), msgs);
+            }
         }
+        if let Some(ctx) = &mut self.current_channel && ctx.server_name == old {
+            ctx.server_name = new.to_string();
+        }
+        if self.last_server.as_deref() == Some(old) {
+            self.last_server = Some(new.to_string());
+        }
+        self.rebuild_server_tree();
+    }
+
+    // Only one connection is open at a time, so the first autoconnect server wins
+    pub fn autoconnect_server(&self) -> Option<String> {
+        self.servers.iter().find(|s| s.autoconnect).map(|s| s.name.clone())
+    }
+
+    pub fn is_ignored(&self, nick: &str) -> bool {
+        self.config.ignore.iter().any(|mask| mask_matches(mask, nick))
     }
 
     pub fn set_yank(&mut self, text: String) {
         // 1. Store in the internal buffer (for pasting within the app with 'p')
         self.yank = text.clone();
 
-        // 2. Copy to the system's Wayland clipboard
-        let opts = Options::new();
-        opts.copy(
-            Source::Bytes(text.into_bytes().into()),
-            MimeType::Autodetect,
-        ).ok();
+        // 2. Copy to the system clipboard, Wayland or X11
+        let copied = clipboard::copy(&text);
+
+        // Without a clipboard every yank would fail the same way, say so once
+        if copied.is_err() && !self.clipboard_warned {
+            self.clipboard_warned = true;
+            self.push_system_to_current("Clipboard unavailable, yanked to the internal register only".to_string());
+        }
+    }
+
+    // Insert the system clipboard at the cursor
+    pub fn paste_clipboard(&mut self) {
+        let Some(text) = clipboard::paste() else {
+            self.push_system_to_current("✖ Clipboard is empty or unavailable".to_string());
+            return;
+        };
+        self.insert_paste(&text);
+    }
+
+    // Pasted text keeps its newlines, Enter then sends one message per line
+    pub fn insert_paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self.vim_mode {
+            VimMode::Command => {
+                for c in text.trim_end_matches('\n').chars() {
+                    self.insert_cmd_char(if c == '\n' { ' ' } else { c });
+                }
+            }
+            VimMode::Normal | VimMode::Insert | VimMode::Vimless => {
+                self.insert_msg_str(text.trim_end_matches('\n'));
+            }
+            _ => {}
+        }
+    }
+
+    // Send the input as one PRIVMSG per line, asking first when a paste would flood
+    pub fn send_msg_lines(&mut self, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        let text: String = self.get_msg_iter().collect();
+        let lines: Vec<String> = text
+            .split('\n')
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.to_string())
+            .collect();
+
+        if lines.len() > self.config.ui.paste_confirm_lines && self.paste_confirm.as_ref() != Some(&text) {
+            self.paste_confirm = Some(text);
+            self.push_system_to_current(format!(
+                "About to send {} lines, press Enter again to send or Esc to cancel",
+                lines.len()
+            ));
+            return;
+        }
+
+        self.paste_confirm = None;
+        self.take_msg_text();
+        for line in lines {
+            irc_tx.send(IrcCommand::PrivMsg(line.clone())).ok();
+            self.echo_own_msg(&line);
+        }
+    }
+
+    // With echo-message the server sends our messages back with its own timestamp,
+    // showing them here too would double them
+    pub fn echo_own_msg(&mut self, text: &str) {
+        self.echo_own_msg_tagged(text, MessageTags::default());
+    }
+
+    // A reply keeps the msgid it answers, so our copy gets the quote above it too
+    pub fn echo_own_msg_tagged(&mut self, text: &str, tags: MessageTags) {
+        if !self.enabled_caps.iter().any(|c| c == "echo-message") {
+            let nick = self.current_nick.clone();
+            let (server_name, channel_name) = self.get_current_channel_key().unwrap_or_else(system_buffer_key);
+            self.push_user_msg_to(&server_name, &channel_name, &nick, text, Local::now(), tags);
+            if let Some((_, channel_name)) = self.get_current_channel_key() {
+                self.recent_sent.push((channel_name, text.to_string(), Instant::now()));
+            }
+        }
+        // Sending while scrolled up into history brings the view back down to see it land,
+        // following the bottom also catches the server's echo when it comes
+        self.msg_jump_bottom();
+    }
+
+    // R's Replace lasts as long as the Insert mode it started, however that was left.
+    // Called before every key and mouse event, so a later i or a inserts again
+    pub fn end_replace_outside_insert(&mut self) {
+        if self.vim_mode != VimMode::Insert {
+            self.overwrite = false;
+        }
+    }
+
+    // Quit, unless that would throw away a message that was never sent
+    pub fn request_quit(&mut self) {
+        if self.msg.is_empty() {
+            self.should_quit = true;
+        } else {
+            self.quit_confirm = true;
+        }
+    }
+
+    // Called on every keypress, comes back from auto-away
+    pub fn note_activity(&mut self, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        self.last_activity = Instant::now();
+        if self.auto_away {
+            self.auto_away = false;
+            irc_tx.send(IrcCommand::Away(None)).ok();
+        }
+    }
+
+    // Called every loop iteration, goes away once the keyboard has been idle long enough
+    pub fn check_auto_away(&mut self, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        let minutes = self.config.auto_away_minutes;
+        if minutes == 0 || self.auto_away || self.connection_state != ConnectionState::Connected {
+            return;
+        }
+        if self.last_activity.elapsed() >= Duration::from_secs(minutes * 60) {
+            self.auto_away = true;
+            irc_tx.send(IrcCommand::Away(Some(AUTO_AWAY_MESSAGE.to_string()))).ok();
+        }
+    }
+
+    // Bouncers and some servers send our own messages back even without echo-message.
+    // An incoming copy of something we just echoed locally is dropped, once.
+    pub fn is_self_echo(&mut self, channel_name: &str, nick: &str, text: &str) -> bool {
+        let now = Instant::now();
+        self.recent_sent.retain(|(_, _, sent)| now.duration_since(*sent) < SELF_ECHO_WINDOW);
+        if !nick.eq_ignore_ascii_case(&self.current_nick) {
+            return false;
+        }
+        match self.recent_sent
+            .iter()
+            .position(|(channel, sent_text, _)| channel.eq_ignore_ascii_case(channel_name) && sent_text == text)
+        {
+            Some(pos) => {
+                self.recent_sent.remove(pos);
+                true
+            }
+            None => false,
+        }
     }
 
+    // Without a channel the message pane shows the system buffer
     pub fn get_current_messages(&self) -> Option<&ChannelMessages> {
-        let (server_name, channel_name) = self.get_current_channel_key()?;
-        self.channel_messages.get(&(server_name, channel_name))
+        match self.get_current_channel_key() {
+            Some(key) => self.channel_messages.get(&key),
+            None => self.channel_messages.get(&system_buffer_key()),
+        }
     }
 
     pub fn get_current_messages_mut(&mut self) -> Option<&mut ChannelMessages> {
-        let (server_name, channel_name) = self.get_current_channel_key()?;
-        self.channel_messages.get_mut(&(server_name, channel_name))
+        match self.get_current_channel_key() {
+            Some(key) => self.channel_messages.get_mut(&key),
+            None => Some(self.channel_messages.entry(system_buffer_key()).or_default()),
+        }
+    }
+
+    // Switch the viewed channel, keeping unsent input as a per-channel draft
+    pub fn set_current_channel(&mut self, ctx: Option<ChannelContext>) {
+        let draft: String = self.msg.iter().collect();
+        let draft_cursor = self.msg_cursor;
+        let stashed = if let Some(msgs) = self.get_current_messages_mut() {
+            msgs.draft = draft;
+            msgs.draft_cursor = draft_cursor;
+            msgs.last_read_index = Some(msgs.messages.len());
+            true
+        } else {
+            false
+        };
+
+        self.current_channel = ctx;
+        self.msg_sel_start = None;
+        self.load_history();
+
+        if let Some(current) = self.current_channel.clone() {
+            for channel in self.servers
+                .iter_mut()
+                .filter(|s| s.name == current.server_name)
+                .flat_map(|s| s.channels.iter_mut())
+                .filter(|c| c.name == current.channel_name)
+            {
+                channel.highlighted = false;
+                channel.unread_count = 0;
+            }
+        }
+
+        if let Some(msgs) = self.get_current_messages() {
+            let (draft, draft_cursor) = (msgs.draft.clone(), msgs.draft_cursor);
+            self.msg = draft.chars().collect();
+            self.msg_cursor = draft_cursor.min(self.msg.len());
+        } else if stashed {
+            // new channel has no buffer yet, so it has no draft either
+            self.clear_msg();
+        }
     }
 
     fn get_current_channel_key(&self) -> Option<(String, String)> {
         self.current_channel.as_ref().map(|ctx| (ctx.server_name.clone(), ctx.channel_name.clone()))
     }
 
+    // The join key of a channel on the connected server, if it was joined with one
+    fn join_key(&self, channel: &str) -> Option<String> {
+        self.servers.iter()
+            .find(|s| s.is_connected)
+            .and_then(|s| s.channels.iter().find(|c| c.name.eq_ignore_ascii_case(channel)))
+            .and_then(|c| c.key.clone())
+    }
+
+    // Held until NAMES confirms the join, a key the server refused never reaches the tree
+    fn remember_join_key(&mut self, server_name: &str, channel: &str, key: Option<String>) {
+        let pending = (server_name.to_string(), channel.to_ascii_lowercase());
+        match key {
+            Some(key) => self.pending_join_keys.insert(pending, key),
+            None => self.pending_join_keys.remove(&pending),
+        };
+    }
+
+    // The key a confirmed join was sent with, to keep on its ChannelInfo
+    pub fn take_join_key(&mut self, server_name: &str, channel: &str) -> Option<String> {
+        self.pending_join_keys.remove(&(server_name.to_string(), channel.to_ascii_lowercase()))
+    }
+
+    // Only the welcome banner goes through here
     pub fn push_without_updating_scroll(&mut self, text: String) {
         if let Some(msgs) = self.get_current_messages_mut() {
             msgs.messages.push(ColoredMessage {
                 nick: None,
                 text,
                 color: None,
+                time: Local::now(),
+                presence: Vec::new(),
+                banner: true,
+                historical: false,
+                msgid: None,
+                reply_to: None,
             });
         }
     }
@@ -199,6 +888,8 @@ impl App {
             VimMode::Messages => VimMode::Clients,
             VimMode::Clients => VimMode::Normal,
             VimMode::Vimless => VimMode::Vimless,
+            VimMode::Help => VimMode::Help,
+            VimMode::ChannelList => VimMode::ChannelList,
         };
     }
 
@@ -227,75 +918,465 @@ impl App {
 
     }
 
-    pub fn clear_messages(&mut self) {
+    pub fn clear_view(&mut self) {
         if let Some(msgs) = self.get_current_messages_mut() {
-            msgs.messages.clear();
-            msgs.msg_index = 0;
-            msgs.msg_scroll = 0;
+            msgs.clear_view();
+        }
+    }
+
+    pub fn clear_buffer(&mut self) {
+        if let Some(msgs) = self.get_current_messages_mut() {
+            msgs.clear_buffer();
+        }
+    }
+
+    // save-buffer, writes the scrollback in the same format as the logs
+    pub fn save_buffer(&mut self, path: &str) {
+        let Some(msgs) = self.get_current_messages() else {
+            return;
+        };
+        let contents: String = msgs.messages
+            .iter()
+            .map(|m| logging::format_line(m.time, m.nick.as_deref(), &m.text))
+            .collect();
+        let count = msgs.messages.len();
+
+        let path = logging::expand_home(path);
+        match std::fs::write(&path, contents) {
+            Ok(()) => self.push_system_to_current(format!("Saved {} messages to {}", count, path.display())),
+            Err(e) => self.push_system_to_current(format!("✖ Failed to save {}: {}", path.display(), e)),
+        }
+    }
+
+    // Wipe every channel's scrollback, e.g. before handing the terminal to someone
+    pub fn clear_all_buffers(&mut self) {
+        for msgs in self.channel_messages.values_mut() {
+            msgs.clear_buffer();
         }
     }
 
+    // Open the help overlay, Esc goes back to whatever prev_mode holds
+    pub fn open_help(&mut self) {
+        self.vim_mode = VimMode::Help;
+        self.help_scroll = 0;
+    }
+
+    pub fn scroll_help_up(&mut self) {
+        self.help_scroll = self.help_scroll.saturating_sub(1);
+    }
+
+    pub fn scroll_help_down(&mut self) {
+        // clamped against the popup height when rendering
+        self.help_scroll += 1;
+    }
+
+    // Open the /list popup, entries arrive later through extend_channel_list
+    pub fn open_channel_list(&mut self) {
+        self.vim_mode = VimMode::ChannelList;
+        self.channel_list.clear();
+        self.channel_list_filter.clear();
+        self.channel_list_index = 0;
+        self.channel_list_scroll = 0;
+        self.channel_list_done = false;
+    }
+
+    pub fn extend_channel_list(&mut self, entries: Vec<ChannelListEntry>, done: bool) {
+        let room = MAX_CHANNEL_LIST.saturating_sub(self.channel_list.len());
+        self.channel_list.extend(entries.into_iter().take(room));
+        self.channel_list_done = done;
+    }
+
+    pub fn filtered_channel_list(&self) -> Vec<&ChannelListEntry> {
+        let filter = self.channel_list_filter.to_lowercase();
+        self.channel_list
+            .iter()
+            .filter(|e| {
+                filter.is_empty()
+                    || e.name.to_lowercase().contains(&filter)
+                    || e.topic.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    pub fn push_channel_list_filter(&mut self, c: char) {
+        self.channel_list_filter.push(c);
+        self.channel_list_index = 0;
+        self.channel_list_scroll = 0;
+    }
+
+    pub fn pop_channel_list_filter(&mut self) {
+        self.channel_list_filter.pop();
+        self.channel_list_index = 0;
+        self.channel_list_scroll = 0;
+    }
+
+    pub fn move_channel_list_up(&mut self, amount: usize) {
+        self.channel_list_index = self.channel_list_index.saturating_sub(amount);
+    }
+
+    pub fn move_channel_list_down(&mut self, amount: usize) {
+        let len = self.filtered_channel_list().len();
+        self.channel_list_index = (self.channel_list_index + amount).min(len.saturating_sub(1));
+    }
+
+    pub fn join_selected_list_channel(&mut self, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        let Some(name) = self.filtered_channel_list()
+            .get(self.channel_list_index)
+            .map(|e| e.name.clone())
+        else {
+            return;
+        };
+        self.return_to_prev_mode();
+        self.execute_command(&format!("join {}", name), irc_tx);
+    }
+
     pub fn return_to_prev_mode(&mut self) {
         let temp = self.prev_mode.clone();
         self.vim_mode = self.prev_mode.clone().unwrap_or(VimMode::Normal);
         self.prev_mode = temp;
     }
 
+    fn log_message(&mut self, server_name: &str, channel_name: &str, nick: Option<&str>, text: &str, time: DateTime<Local>) {
+        if self.config.logging && !self.demo && (server_name, channel_name) != SYSTEM_BUFFER {
+            self.chat_log.append(server_name, channel_name, logging::format_line(time, nick, text));
+        }
+    }
+
+    // Fill the front of a channel's buffer from its log the first time it is opened
+    fn load_history(&mut self) {
+        let lines = self.config.log_preload_lines;
+        let Some((server_name, channel_name)) = self.get_current_channel_key() else {
+            return;
+        };
+        if lines == 0 || channel_name == "status" {
+            return;
+        }
+
+        let msgs = self.channel_messages
+            .entry((server_name.clone(), channel_name.clone()))
+            .or_default();
+        if msgs.history_loaded {
+            return;
+        }
+        msgs.history_loaded = true;
+
+        // Whatever this session already received may be in the log too
+        let oldest = msgs.messages.first().map(|m| m.time);
+        let history: Vec<ColoredMessage> = logging::read_tail(&server_name, &channel_name, lines)
+            .into_iter()
+            .filter(|(time, _, _)| oldest.is_none_or(|oldest| *time < oldest))
+            .map(|(time, nick, text)| ColoredMessage {
+                color: nick.as_deref().map(color_for_user),
+                nick,
+                text,
+                time,
+                presence: Vec::new(),
+                banner: false,
+                historical: true,
+                msgid: None,
+                reply_to: None,
+            })
+            .collect();
+        msgs.prepend_history(history);
+    }
+
+    // Write buffered log lines, force skips the wait between flushes (e.g. on quit)
+    pub fn flush_logs(&mut self, force: bool) {
+        let max_bytes = self.config.log_max_kb * 1024;
+        let flushed = if force {
+            self.chat_log.flush(max_bytes)
+        } else {
+            self.chat_log.flush_if_due(max_bytes)
+        };
+        match flushed {
+            Ok(()) => self.log_failed = false,
+            // Not logged itself, that would only queue more lines for the failing write
+            Err(e) if !self.log_failed => {
+                self.log_failed = true;
+                self.push_unlogged_system_to_current(format!("✖ Failed to write logs, will keep retrying: {}", e));
+            }
+            Err(_) => {}
+        }
+    }
+
     // Push a normal system message
     pub fn push_system_to_current(&mut self, text: String) {
+        if let Some((server_name, channel_name)) = self.get_current_channel_key() {
+            self.log_message(&server_name, &channel_name, None, &text, Local::now());
+        }
+        self.push_unlogged_system_to_current(text);
+    }
+
+    fn push_unlogged_system_to_current(&mut self, text: String) {
         if let Some(msgs) = self.get_current_messages_mut() {
-            let msg_len_before = msgs.messages.len();
-            
-            msgs.messages.push(ColoredMessage {
+            msgs.push(ColoredMessage {
                 nick: None,
                 text,
                 color: None,
+                time: Local::now(),
+                presence: Vec::new(),
+                banner: false,
+                historical: false,
+                msgid: None,
+                reply_to: None,
             });
-            
-            // Check if we were at bottom before adding
-            let was_at_bottom = if msg_len_before > 0 {
-                msgs.msg_index == msg_len_before - 1
-            } else {
-                true // Empty list means we're "at bottom"
-            };
-            
-            if was_at_bottom {
-                msgs.msg_index = msgs.messages.len().saturating_sub(1);
-                if msgs.viewport_height > 0 {
-                    msgs.msg_scroll = msgs.messages
-                        .len()
-                        .saturating_sub(msgs.viewport_height);
-                }
-            }
         }
     }
 
+    // Switch to the server's status buffer, which outlives the connection so
+    // disconnect and error messages have somewhere to go
+    pub fn show_status_buffer(&mut self, server_name: &str) {
+        self.channel_messages
+            .entry((server_name.to_string(), "status".to_string()))
+            .or_default();
+        self.set_current_channel(Some(ChannelContext {
+            server_name: server_name.to_string(),
+            channel_name: "status".to_string(),
+        }));
+        self.channel.clear();
+        self.set_clients(Vec::new());
+    }
+
+    // Push a system message into a channel's buffer, creating the buffer if needed
+    pub fn push_system_to(&mut self, server_name: &str, channel_name: &str, text: String) {
+        self.log_message(server_name, channel_name, None, &text, Local::now());
+        self.channel_messages
+            .entry((server_name.to_string(), channel_name.to_string()))
+            .or_default()
+            .push(ColoredMessage {
+                nick: None,
+                text,
+                color: None,
+                time: Local::now(),
+                presence: Vec::new(),
+                banner: false,
+                historical: false,
+                msgid: None,
+                reply_to: None,
+            });
+    }
+
+    pub fn push_presence(&mut self, server_name: &str, channel_name: Option<&str>, kind: PresenceKind, text: String, time: DateTime<Local>) {
+        let collapse = self.config.ui.collapse_presence;
+        let key = match channel_name {
+            Some(channel) => (server_name.to_string(), channel.to_string()),
+            None => match self.get_current_channel_key() {
+                Some(key) => key,
+                None => return,
+            },
+        };
+        self.log_message(&key.0, &key.1, None, &text, time);
+        self.channel_messages
+            .entry(key)
+            .or_default()
+            .push_presence(kind, text, time, collapse);
+    }
+
     // Push a user message with optional colored nick
     pub fn push_user_msg_to_current(&mut self, nick: &str, text: &str) {
-        if let Some(msgs) = self.get_current_messages_mut() {
-            let msg_len_before = msgs.messages.len();
-            
-            msgs.messages.push(ColoredMessage {
-                nick: Some(nick.to_string()),
-                text: text.to_string(),
-                color: Some(color_for_user(nick)),
-            });
-            
-            // Check if we were at bottom before adding
-            let was_at_bottom = if msg_len_before > 0 {
-                msgs.msg_index == msg_len_before - 1
-            } else {
-                true // Empty list means we're "at bottom"
-            };
-            
-            if was_at_bottom {
-                msgs.msg_index = msgs.messages.len().saturating_sub(1);
-                if msgs.viewport_height > 0 {
-                    msgs.msg_scroll = msgs.messages
-                        .len()
-                        .saturating_sub(msgs.viewport_height);
-                }
+        let (server_name, channel_name) = self.get_current_channel_key().unwrap_or_else(system_buffer_key);
+        self.push_user_msg_to(&server_name, &channel_name, nick, text, Local::now(), MessageTags::default());
+    }
+
+    // Push a user message into a channel's buffer, creating the buffer if needed
+    pub fn push_user_msg_to(
+        &mut self,
+        server_name: &str,
+        channel_name: &str,
+        nick: &str,
+        text: &str,
+        time: DateTime<Local>,
+        tags: MessageTags,
+    ) {
+        self.log_message(server_name, channel_name, Some(nick), text, time);
+        let msgs = self.channel_messages
+            .entry((server_name.to_string(), channel_name.to_string()))
+            .or_default();
+        msgs.push(ColoredMessage {
+            nick: Some(nick.to_string()),
+            text: text.to_string(),
+            color: Some(color_for_user(nick)),
+            time,
+            presence: Vec::new(),
+            banner: false,
+            historical: false,
+            msgid: tags.msgid,
+            reply_to: tags.reply_to,
+        });
+    }
+
+    // draft/message-redaction, the message stays so indices don't shift but its text goes
+    pub fn redact_message(&mut self, server_name: &str, channel_name: &str, msgid: &str) {
+        let Some(msgs) = self.channel_messages.get_mut(&(server_name.to_string(), channel_name.to_string())) else {
+            return;
+        };
+        if let Some(msg) = msgs.messages.iter_mut().find(|m| m.msgid.as_deref() == Some(msgid)) {
+            msg.text = REDACTED_TEXT.to_string();
+        }
+    }
+
+    pub fn is_current_channel(&self, server_name: &str, channel_name: &str) -> bool {
+        self.current_channel
+            .as_ref()
+            .is_some_and(|ctx| ctx.server_name == server_name && ctx.channel_name == channel_name)
+    }
+
+    // Does the text mention our nick (as a whole word) or one of the configured highlight words
+    pub fn is_highlight(&self, text: &str) -> bool {
+        let is_nick_char = |c: char| c.is_alphanumeric() || "_-[]\\`^{}|".contains(c);
+        let mentions_nick = !self.current_nick.is_empty()
+            && text
+                .split(|c: char| !is_nick_char(c))
+                .any(|word| word.eq_ignore_ascii_case(&self.current_nick));
+
+        let lower = text.to_lowercase();
+        mentions_nick
+            || self.config.highlights
+                .iter()
+                .any(|word| !word.is_empty() && lower.contains(&word.to_lowercase()))
+    }
+
+    pub fn mark_unread(&mut self, server_name: &str, channel_name: &str) {
+        if let Some(channel) = self.servers
+            .iter_mut()
+            .filter(|s| s.name == server_name)
+            .flat_map(|s| s.channels.iter_mut())
+            .find(|c| c.name == channel_name)
+        {
+            channel.unread_count += 1;
+        }
+    }
+
+    fn channel_info(&self, server_name: &str, channel_name: &str) -> Option<&ChannelInfo> {
+        self.servers
+            .iter()
+            .filter(|s| s.name == server_name)
+            .flat_map(|s| s.channels.iter())
+            .find(|c| c.name.eq_ignore_ascii_case(channel_name))
+    }
+
+    // A changed topic is announced as it happens, the one sent on join is shown once its setter arrives
+    pub fn update_topic(
+        &mut self,
+        server_name: &str,
+        channel_name: &str,
+        topic: Option<String>,
+        set_by: Option<String>,
+        set_at: Option<DateTime<Local>>,
+    ) {
+        let Some(server) = self.servers.iter_mut().find(|s| s.name == server_name) else {
+            return;
+        };
+        // On join the topic comes before NAMES, which is what normally adds the channel
+        let channel = match server.channels.iter().position(|c| c.name.eq_ignore_ascii_case(channel_name)) {
+            Some(pos) => &mut server.channels[pos],
+            None => {
+                server.channels.push(ChannelInfo {
+                    name: channel_name.to_string(),
+                    is_joined: true,
+                    ..Default::default()
+                });
+                server.channels.last_mut().expect("just pushed")
             }
+        };
+
+        if topic.is_some() {
+            channel.topic = topic.clone();
+        }
+        if set_by.is_some() {
+            channel.topic_set_by = set_by.clone();
+            channel.topic_set_at = set_at;
+        }
+
+        // Only a live TOPIC carries both, on join the topic shows in the join summary
+        if let (Some(topic), Some(nick)) = (topic, set_by) {
+            self.push_system_to(server_name, channel_name, format!("{} changed the topic to: {}", nick, topic));
+        }
+    }
+
+    pub fn show_topic(&mut self, server_name: &str, channel_name: &str) {
+        let Some(channel) = self.channel_info(server_name, channel_name).cloned() else {
+            return;
+        };
+        let Some(topic) = &channel.topic else {
+            self.push_system_to(server_name, channel_name, format!("No topic is set for {}", channel_name));
+            return;
+        };
+        self.push_system_to(server_name, channel_name, format!("Topic: {}", topic));
+        self.show_topic_setter(server_name, channel_name, &channel);
+    }
+
+    // "Joined #rust — 1423 users — topic: ...", once the join's NAMES are in
+    pub fn show_join_summary(&mut self, server_name: &str, channel_name: &str, count: usize) {
+        let channel = self.channel_info(server_name, channel_name).cloned().unwrap_or_default();
+        let users = if count == 1 { "1 user".to_string() } else { format!("{} users", count) };
+        let topic = match &channel.topic {
+            Some(topic) => format!("topic: {}", topic),
+            None => "no topic".to_string(),
+        };
+        self.push_system_to(server_name, channel_name, format!("Joined {} — {} — {}", channel_name, users, topic));
+        self.show_topic_setter(server_name, channel_name, &channel);
+    }
+
+    fn show_topic_setter(&mut self, server_name: &str, channel_name: &str, channel: &ChannelInfo) {
+        match (channel.topic_set_by.clone(), channel.topic_set_at) {
+            (Some(nick), Some(time)) => self.push_system_to(
+                server_name,
+                channel_name,
+                format!("Topic set by {} at {}", nick, time.format(TOPIC_TIME_FORMAT)),
+            ),
+            (Some(nick), None) => self.push_system_to(server_name, channel_name, format!("Topic set by {}", nick)),
+            _ => {}
+        }
+    }
+
+    // The first channel of the connected server after the current one (wrapping around) with unread messages
+    pub fn next_unread_channel(&self) -> Option<ChannelContext> {
+        let channels: Vec<(ChannelContext, usize)> = self.servers
+            .iter()
+            .filter(|server| server.is_connected)
+            .flat_map(|server| {
+                server.channels.iter().map(|c| {
+                    (
+                        ChannelContext {
+                            server_name: server.name.clone(),
+                            channel_name: c.name.clone(),
+                        },
+                        c.unread_count,
+                    )
+                })
+            })
+            .collect();
+        let start = self.current_channel
+            .as_ref()
+            .and_then(|cur| {
+                channels
+                    .iter()
+                    .position(|(c, _)| c.server_name == cur.server_name && c.channel_name == cur.channel_name)
+            })
+            .map_or(0, |i| i + 1);
+
+        (0..channels.len())
+            .map(|offset| &channels[(start + offset) % channels.len()])
+            .find(|(_, unread)| *unread > 0)
+            .map(|(ctx, _)| ctx.clone())
+    }
+
+    pub fn jump_to_unread(&mut self, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        match self.next_unread_channel() {
+            Some(ctx) => self.switch_to_channel(ctx, irc_tx),
+            None => self.push_system_to_current("No unread messages".to_string()),
+        }
+    }
+
+    pub fn mark_highlight(&mut self, server_name: &str, channel_name: &str) {
+        if let Some(channel) = self.servers
+            .iter_mut()
+            .filter(|s| s.name == server_name)
+            .flat_map(|s| s.channels.iter_mut())
+            .find(|c| c.name == channel_name)
+        {
+            channel.highlighted = true;
         }
     }
 
@@ -320,6 +1401,31 @@ impl App {
         self.msg_cursor = pos;
     }
 
+    // Ctrl-w, removes what b would move over
+    pub fn delete_msg_word_back(&mut self) {
+        let end = self.msg_cursor;
+        self.move_msg_cursor_back_word();
+        self.take_msg_from_cursor_to_x(end);
+    }
+
+    // Ctrl-u, the removed text can be put back with p
+    pub fn delete_msg_to_start(&mut self) {
+        let end = self.msg_cursor;
+        self.msg_cursor = 0;
+        let text = self.take_msg_from_cursor_to_x(end);
+        if !text.is_empty() {
+            self.set_yank(text);
+        }
+    }
+
+    // Ctrl-k, same for the rest of the line
+    pub fn delete_msg_to_end(&mut self) {
+        let text = self.take_msg_from_cursor_to_x(self.msg.len());
+        if !text.is_empty() {
+            self.set_yank(text);
+        }
+    }
+
     pub fn move_msg_cursor_back_word_uppercase(&mut self) {
         if self.msg_cursor == 0 {
             return;
@@ -385,28 +1491,23 @@ impl App {
 
     pub fn move_msg_cursor_end_of_word(&mut self) {
         let len = self.msg.len();
-        if self.msg_cursor >= len {
+        if self.msg_cursor + 1 >= len {
             return;
         }
 
-        let mut pos = self.msg_cursor;
+        // Start one past the cursor so e from the last character moves on to the next word
+        let mut pos = self.msg_cursor + 1;
         
-        // Skip to end of current word
-        while pos < len && !self.msg[pos].is_whitespace() {
+        // Skip whitespace between words
+        while pos < len && self.msg[pos].is_whitespace() {
             pos += 1;
         }
-        
-        // We're now at whitespace or end of line
-        // If not at end and there's another word, skip whitespace and go to end of next word
-        if pos < len && self.msg[pos].is_whitespace() {
-            // Skip whitespace
-            while pos < len && self.msg[pos].is_whitespace() {
-                pos += 1;
-            }
-            // Go to end of next word
-            while pos < len && !self.msg[pos].is_whitespace() {
-                pos += 1;
-            }
+        if pos >= len {
+            return; // only trailing whitespace left
+        }
+        // Go to end of this word
+        while pos < len && !self.msg[pos].is_whitespace() {
+            pos += 1;
         }
         
         // Move back to last character of word (not the whitespace after it)
@@ -420,32 +1521,28 @@ impl App {
     // uppercase E
     pub fn move_msg_cursor_end_of_word_uppercase(&mut self) {
         let len = self.msg.len();
-        if self.msg_cursor >= len {
+        if self.msg_cursor + 1 >= len {
             return;
         }
 
-        let mut pos = self.msg_cursor;
+        let mut pos = self.msg_cursor + 1;
         
-        // Move to end of current non-whitespace sequence (WORD)
+        // Skip whitespace, then move to end of the non-whitespace sequence (WORD)
+        while pos < len && self.msg[pos].is_whitespace() {
+            pos += 1;
+        }
+        if pos >= len {
+            return; // only trailing whitespace left
+        }
         while pos < len && !self.msg[pos].is_whitespace() {
             pos += 1;
         }
         
-        // If we hit whitespace and there's more content
-        if pos < len && self.msg[pos].is_whitespace() {
-            // Skip all whitespace
-            while pos < len && self.msg[pos].is_whitespace() {
-                pos += 1;
-            }
-            // Move to end of next WORD
-            while pos < len && !self.msg[pos].is_whitespace() {
-                pos += 1;
-            }
+        // Position at last character of WORD (not the whitespace after it)
+        if pos > 0 && pos <= len {
+            pos -= 1;
         }
         
-        // Position at last character of WORD
-        let _ = pos.saturating_sub(1);
-        
         self.msg_cursor = pos;
     }
 
@@ -457,6 +1554,13 @@ impl App {
         self.msg.insert(self.msg_cursor, c);
         self.msg_cursor += 1;
     }
+    // Put c over the character under the cursor, or append it at the end
+    pub fn replace_msg_char(&mut self, c: char) {
+        if self.msg_cursor < self.msg.len() {
+            self.msg.remove(self.msg_cursor);
+        }
+        self.msg.insert(self.msg_cursor, c);
+    }
     pub fn delete_msg_char(&mut self) {
         if self.msg_cursor == 0 {
             return;
@@ -566,6 +1670,11 @@ impl App {
         self.msg_cursor = self.msg.len();
     }
 
+    // vim's N|, columns count from 1 and stop at the last character
+    pub fn move_msg_cursor_to_column(&mut self, column: usize) {
+        self.msg_cursor = column.saturating_sub(1).min(self.msg.len().saturating_sub(1));
+    }
+
     pub fn take_msg_from_cursor_to_x(&mut self, x: usize) -> String {
         let start = self.msg_cursor.min(self.msg.len());
         let end = x.min(self.msg.len());
@@ -577,54 +1686,216 @@ impl App {
             let c = self.msg.remove(self.msg_cursor);
             result.push(c);
         }
-        result
+        result
+    }
+
+    // Row and column of the cursor once the input is split on its newlines
+    pub fn msg_cursor_row_col(&self) -> (usize, usize) {
+        let mut row = 0;
+        let mut col = 0;
+        for c in self.get_msg_iter().take(self.msg_cursor) {
+            if c == '\n' {
+                row += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+        }
+        (row, col)
+    }
+
+    // Rows the input box needs for the current input
+    pub fn input_rows(&self) -> usize {
+        (self.get_msg_iter().filter(|c| *c == '\n').count() + 1).min(MAX_INPUT_ROWS)
+    }
+
+    pub fn clear_msg(&mut self) {
+        self.msg.clear();
+        self.msg_cursor = 0;
+    }
+
+    pub fn take_msg_text(&mut self) -> String {
+        self.msg_cursor = 0;
+        self.msg.drain(..).collect()
+    }
+
+    // ----------------- Command Buffer Methods ----------------
+    pub fn insert_cmd_char(&mut self, c: char) {
+        if self.cmd_cursor > self.cmd.len() {
+            return;
+        }
+        self.cmd.insert(self.cmd_cursor, c);
+        self.cmd_cursor += 1;
+    }
+    pub fn delete_cmd_char(&mut self) {
+        if self.cmd_cursor == 0 {
+            return;
+        }
+        self.cmd.remove(self.cmd_cursor.saturating_sub(1));
+        self.cmd_cursor = self.cmd_cursor.saturating_sub(1);
+    }
+    pub fn move_cmd_cursor_left(&mut self) {
+        if self.cmd_cursor == 0 {
+            return;
+        }
+        self.cmd_cursor = self.cmd_cursor.saturating_sub(1);
+    }
+
+    pub fn move_cmd_cursor_right(&mut self) {
+        if self.cmd_cursor >= self.cmd.len() {
+            return;
+        }
+        self.cmd_cursor += 1;
+    }
+
+    pub fn cmd_cursor_position(&self) -> usize {
+        self.cmd_cursor
+    }
+
+    // Where the word before the cursor starts and the members whose nick it begins
+    fn nick_matches(&self) -> Option<(usize, Vec<String>)> {
+        let start = (0..self.msg_cursor)
+            .rev()
+            .find(|&i| self.msg[i].is_whitespace())
+            .map_or(0, |i| i + 1);
+        let prefix: String = (start..self.msg_cursor).map(|i| self.msg[i]).collect::<String>().to_lowercase();
+        if prefix.is_empty() {
+            return None;
+        }
+        let matches: Vec<String> = self.clients
+            .iter()
+            .map(|c| c.name.clone())
+            .filter(|name| name.to_lowercase().starts_with(&prefix) && *name != self.current_nick)
+            .collect();
+        if matches.is_empty() {
+            return None;
+        }
+        Some((start, matches))
+    }
+
+    // Tab: complete a lone match, or list several in the popup. False when there is nothing to complete
+    pub fn complete_nick(&mut self) -> bool {
+        if let Some(popup) = &mut self.nick_popup {
+            if popup.active {
+                self.accept_nick_popup();
+            } else {
+                popup.active = true;
+            }
+            return true;
+        }
+        let Some((start, matches)) = self.nick_matches() else {
+            return false;
+        };
+        if matches.len() == 1 {
+            self.insert_nick(start, &matches[0]);
+        } else {
+            self.nick_popup = Some(NickPopup { start, matches, index: 0, active: true });
+        }
+        true
     }
 
-    pub fn msg_cursor_position(&self) -> usize {
-        self.msg_cursor
+    fn insert_nick(&mut self, start: usize, nick: &str) {
+        let end = self.msg_cursor;
+        self.msg_cursor = start;
+        self.take_msg_from_cursor_to_x(end);
+        let suffix = if start == 0 { self.config.ui.completion_suffix.clone() } else { " ".to_string() };
+        self.insert_msg_str(&format!("{}{}", nick, suffix));
     }
 
-    pub fn clear_msg(&mut self) {
-        self.msg.clear();
-        self.msg_cursor = 0;
+    pub fn accept_nick_popup(&mut self) {
+        if let Some(popup) = self.nick_popup.take() {
+            self.insert_nick(popup.start, &popup.matches[popup.index]);
+        }
     }
 
-    pub fn take_msg_text(&mut self) -> String {
-        self.msg_cursor = 0;
-        self.msg.drain(..).collect()
+    pub fn move_nick_popup(&mut self, down: bool) {
+        if let Some(popup) = &mut self.nick_popup {
+            let len = popup.matches.len();
+            popup.index = if down { (popup.index + 1) % len } else { (popup.index + len - 1) % len };
+        }
     }
 
-    // ----------------- Command Buffer Methods ----------------
-    pub fn insert_cmd_char(&mut self, c: char) {
-        if self.cmd_cursor > self.cmd.len() {
+    // Show the popup once the word being typed fits several nicks and narrow it as typing goes on,
+    // it closes once nothing matches. A lone match is left for Tab
+    pub fn refresh_nick_popup(&mut self) {
+        let popup = self.nick_popup.take();
+        if !matches!(self.vim_mode, VimMode::Insert | VimMode::Vimless) {
             return;
         }
-        self.cmd.insert(self.cmd_cursor, c);
-        self.cmd_cursor += 1;
-    }
-    pub fn delete_cmd_char(&mut self) {
-        if self.cmd_cursor == 0 {
+        let Some((start, matches)) = self.nick_matches() else {
             return;
+        };
+        let (index, active) = match popup {
+            Some(popup) if popup.start == start => {
+                let selected = &popup.matches[popup.index];
+                (matches.iter().position(|m| m == selected).unwrap_or(0), popup.active)
+            }
+            _ if matches.len() > 1 => (0, false),
+            _ => return,
+        };
+        self.nick_popup = Some(NickPopup { start, matches, index, active });
+    }
+
+    pub fn flash_error(&mut self) {
+        if self.config.ui.flash_on_error {
+            self.error_flash = Some(Instant::now());
         }
-        self.cmd.remove(self.cmd_cursor.saturating_sub(1));
-        self.cmd_cursor = self.cmd_cursor.saturating_sub(1);
     }
-    pub fn move_cmd_cursor_left(&mut self) {
-        if self.cmd_cursor == 0 {
+
+    pub fn is_flashing(&self) -> bool {
+        self.error_flash.is_some_and(|t| t.elapsed() < ERROR_FLASH)
+    }
+
+    // Called on Enter in Vimless mode, before the input is sent or run
+    pub fn remember_input(&mut self) {
+        self.input_history_index = None;
+        let text: String = self.get_msg_iter().collect();
+        if text.trim().is_empty() || self.input_history.last() == Some(&text) {
             return;
         }
-        self.cmd_cursor = self.cmd_cursor.saturating_sub(1);
+        self.input_history.push(text);
+        if self.input_history.len() > INPUT_HISTORY_LEN {
+            self.input_history.remove(0);
+        }
     }
 
-    pub fn move_cmd_cursor_right(&mut self) {
-        if self.cmd_cursor >= self.cmd.len() {
+    pub fn input_history_prev(&mut self) {
+        let index = match self.input_history_index {
+            Some(0) => return,
+            Some(i) => i - 1,
+            None if self.input_history.is_empty() => return,
+            None => {
+                self.input_history_draft = self.get_msg_iter().collect();
+                self.input_history.len() - 1
+            }
+        };
+        self.input_history_index = Some(index);
+        let text = self.input_history[index].clone();
+        self.replace_msg_text(&text);
+    }
+
+    // Past the newest entry brings back what was being typed before Up
+    pub fn input_history_next(&mut self) {
+        let Some(index) = self.input_history_index else {
             return;
-        }
-        self.cmd_cursor += 1;
+        };
+        let text = if index + 1 < self.input_history.len() {
+            self.input_history_index = Some(index + 1);
+            self.input_history[index + 1].clone()
+        } else {
+            self.input_history_index = None;
+            std::mem::take(&mut self.input_history_draft)
+        };
+        self.replace_msg_text(&text);
     }
 
-    pub fn cmd_cursor_position(&self) -> usize {
-        self.cmd_cursor
+    fn replace_msg_text(&mut self, text: &str) {
+        self.clear_msg();
+        self.insert_msg_str(text);
+    }
+
+    pub fn set_cmd_cursor(&mut self, pos: usize) {
+        self.cmd_cursor = pos.min(self.cmd.len());
     }
 
     pub fn clear_cmd(&mut self) {
@@ -645,10 +1916,212 @@ impl App {
     ) {
         match cmd {
             "quit" | "q" => {
-                self.should_quit = true;
+                self.request_quit();
             }
             "clear" | "c" => {
-                self.clear_messages();
+                self.clear_view();
+            }
+            "clear-buffer" => {
+                self.clear_buffer();
+            }
+            s if s == "save-buffer" || s.starts_with("save-buffer ") => {
+                let path = s["save-buffer".len()..].trim();
+                if path.is_empty() {
+                    self.push_system_to_current("Usage: save-buffer <path>".to_string());
+                    self.push_system_to_current("Example: save-buffer ~/rust-help.log".to_string());
+                    return;
+                }
+                self.save_buffer(path);
+            }
+            "mark-read" => {
+                if let Some(msgs) = self.get_current_messages_mut() {
+                    msgs.last_read_index = None;
+                }
+            }
+            "clear-all" => {
+                self.clear_all_buffers();
+            }
+            "ignore" => {
+                if self.config.ignore.is_empty() {
+                    self.push_system_to_current("Ignore list is empty".to_string());
+                } else {
+                    self.push_system_to_current(format!("Ignoring: {}", self.config.ignore.join(", ")));
+                }
+            }
+            s if s.starts_with("ignore ") => {
+                let nick = s["ignore ".len()..].trim();
+                if nick.is_empty() {
+                    self.push_system_to_current("Usage: ignore <nick|mask>".to_string());
+                    return;
+                }
+                if self.config.ignore.iter().any(|m| m.eq_ignore_ascii_case(nick)) {
+                    self.push_system_to_current(format!("Already ignoring {}", nick));
+                    return;
+                }
+                self.config.ignore.push(nick.to_string());
+                self.save_config();
+                self.push_system_to_current(format!("Ignoring {}", nick));
+            }
+            s if s == "unignore" || s.starts_with("unignore ") => {
+                let nick = s["unignore".len()..].trim();
+                if nick.is_empty() {
+                    self.push_system_to_current("Usage: unignore <nick|mask>".to_string());
+                    return;
+                }
+                let before = self.config.ignore.len();
+                self.config.ignore.retain(|m| !m.eq_ignore_ascii_case(nick));
+                if self.config.ignore.len() == before {
+                    self.push_system_to_current(format!("{} is not ignored", nick));
+                    return;
+                }
+                self.save_config();
+                self.push_system_to_current(format!("No longer ignoring {}", nick));
+            }
+            "notify" => {
+                if self.config.notify.is_empty() {
+                    self.push_system_to_current("Notify list is empty".to_string());
+                } else {
+                    self.push_system_to_current(format!("Watching: {}", self.config.notify.join(", ")));
+                }
+            }
+            s if s.starts_with("notify ") => {
+                let nick = s["notify ".len()..].trim();
+                if nick.is_empty() {
+                    self.push_system_to_current("Usage: notify <nick>".to_string());
+                    return;
+                }
+                if let Err(e) = is_valid_nick(nick, &self.isupport) {
+                    self.push_system_to_current(format!("✖ {}", e));
+                    return;
+                }
+                if self.config.notify.iter().any(|n| n.eq_ignore_ascii_case(nick)) {
+                    self.push_system_to_current(format!("Already watching {}", nick));
+                    return;
+                }
+                self.config.notify.push(nick.to_string());
+                self.save_config();
+                irc_tx.send(IrcCommand::Monitor { nick: nick.to_string(), add: true }).ok();
+                self.push_system_to_current(format!("Watching {}, you'll be told when they come online", nick));
+            }
+            s if s == "unnotify" || s.starts_with("unnotify ") => {
+                let nick = s["unnotify".len()..].trim();
+                if nick.is_empty() {
+                    self.push_system_to_current("Usage: unnotify <nick>".to_string());
+                    return;
+                }
+                let before = self.config.notify.len();
+                self.config.notify.retain(|n| !n.eq_ignore_ascii_case(nick));
+                if self.config.notify.len() == before {
+                    self.push_system_to_current(format!("{} is not on the notify list", nick));
+                    return;
+                }
+                self.save_config();
+                irc_tx.send(IrcCommand::Monitor { nick: nick.to_string(), add: false }).ok();
+                self.push_system_to_current(format!("No longer watching {}", nick));
+            }
+            "help" | "h" => {
+                self.open_help();
+            }
+            "set" => {
+                self.push_system_to_current(format!("wrap = {}", self.config.ui.wrap.as_str()));
+                self.push_system_to_current(format!("logging = {}", if self.config.logging { "on" } else { "off" }));
+                self.push_system_to_current(format!("nick_column = {}", self.config.ui.nick_column));
+                self.push_system_to_current(format!("mouse = {}", if self.config.ui.mouse { "on" } else { "off" }));
+                self.push_system_to_current(format!("tab_bar = {}", if self.config.ui.tab_bar { "on" } else { "off" }));
+                self.push_system_to_current(format!("tree_width = {}", self.config.ui.tree_width));
+                self.push_system_to_current(format!("clients_width = {}", self.config.ui.clients_width));
+            }
+            s if s.starts_with("set ") => {
+                let parts: Vec<&str> = s.split_whitespace().collect();
+                if parts.len() != 3 {
+                    self.push_system_to_current("Usage: set <key> <value>".to_string());
+                    self.push_system_to_current("Example: set wrap off".to_string());
+                    return;
+                }
+                match parts[1] {
+                    "wrap" => {
+                        let Some(wrap) = WrapMode::parse(parts[2]) else {
+                            self.push_system_to_current("wrap must be one of: on, trim, off".to_string());
+                            return;
+                        };
+                        self.config.ui.wrap = wrap;
+                        self.msg_hscroll = 0;
+                    }
+                    "logging" => {
+                        self.config.logging = match parts[2] {
+                            "on" => true,
+                            "off" => false,
+                            _ => {
+                                self.push_system_to_current("logging must be on or off".to_string());
+                                return;
+                            }
+                        };
+                        if !self.config.logging {
+                            self.flush_logs(true);
+                        }
+                    }
+                    "nick_column" => {
+                        let Ok(width) = parts[2].parse() else {
+                            self.push_system_to_current("nick_column must be a number, 0 turns alignment off".to_string());
+                            return;
+                        };
+                        self.config.ui.nick_column = width;
+                    }
+                    // main picks the change up and toggles mouse capture
+                    "mouse" => {
+                        self.config.ui.mouse = match parts[2] {
+                            "on" => true,
+                            "off" => false,
+                            _ => {
+                                self.push_system_to_current("mouse must be on or off".to_string());
+                                return;
+                            }
+                        };
+                    }
+                    // 0 goes back to fitting the tree to its names
+                    "tree_width" | "tree-width" => {
+                        match parts[2].parse::<u16>() {
+                            Ok(width) if width == 0 || width >= MIN_PANEL_WIDTH => self.config.ui.tree_width = width,
+                            _ => {
+                                self.push_system_to_current(format!("tree_width must be 0 or at least {}", MIN_PANEL_WIDTH));
+                                return;
+                            }
+                        }
+                    }
+                    "clients_width" | "clients-width" => {
+                        match parts[2].parse::<u16>() {
+                            Ok(width) if width >= MIN_PANEL_WIDTH => self.config.ui.clients_width = width,
+                            _ => {
+                                self.push_system_to_current(format!("clients_width must be at least {}", MIN_PANEL_WIDTH));
+                                return;
+                            }
+                        }
+                    }
+                    "tab_bar" => {
+                        self.config.ui.tab_bar = match parts[2] {
+                            "on" => true,
+                            "off" => false,
+                            _ => {
+                                self.push_system_to_current("tab_bar must be on or off".to_string());
+                                return;
+                            }
+                        };
+                    }
+                    key => {
+                        self.push_system_to_current(format!("Unknown setting: {}", key));
+                        return;
+                    }
+                }
+                self.save_config();
+                self.push_system_to_current(format!("{} = {}", parts[1], parts[2]));
+            }
+            "list" => {
+                if !self.is_connected {
+                    self.push_system_to_current("Not connected to server yet. Use 'connect <server>' first.".to_string());
+                    return;
+                }
+                irc_tx.send(IrcCommand::ListChannels).ok();
+                self.open_channel_list();
             }
             "Vimless" | "vimless" => {
                 self.vim_mode = VimMode::Vimless;
@@ -662,6 +2135,10 @@ impl App {
                     return;
                 }
                 let nick = parts[1].trim();
+                if let Err(e) = is_valid_nick(nick, &self.isupport) {
+                    self.push_system_to_current(format!("✖ {}", e));
+                    return;
+                }
                 irc_tx.send(IrcCommand::Nick(nick.to_string())).ok();
                 self.current_nick = nick.to_string();
             }
@@ -696,6 +2173,90 @@ impl App {
                     self.push_system_to_current("Disconnected from server.".to_string());
                 }
             }
+            "reconnect" => {
+                let Some(server_name) = self.last_server.clone() else {
+                    self.push_system_to_current("No server to reconnect to. Use 'connect <server>' first.".to_string());
+                    return;
+                };
+
+                // DMs can't be JOINed, their buffers stay in the tree and work again once connected
+                let channels: Vec<(String, Option<String>)> = self.servers
+                    .iter()
+                    .find(|s| s.name == server_name)
+                    .map(|s| s.channels.iter().filter(|c| c.is_joined && !c.is_dm).map(|c| (c.name.clone(), c.key.clone())).collect())
+                    .unwrap_or_default();
+
+                if self.is_connected {
+                    irc_tx.send(IrcCommand::Disconnect).ok();
+                }
+                irc_tx.send(IrcCommand::Connect(server_name.clone())).ok();
+                irc_tx.send(IrcCommand::RejoinAll(channels)).ok();
+                self.push_system_to_current(format!("Reconnecting to {}...", server_name));
+            }
+            s if s == "reply" || s.starts_with("reply ") => {
+                let text = s["reply".len()..].trim();
+                if text.is_empty() {
+                    self.push_system_to_current("Usage: reply <message>, answers the message selected in Messages mode".to_string());
+                    return;
+                }
+                if !self.enabled_caps.iter().any(|c| c == "message-tags") {
+                    self.push_system_to_current("✖ This server doesn't support replies (no message-tags)".to_string());
+                    return;
+                }
+                let Some((_, channel)) = self.get_current_channel_key() else {
+                    self.push_system_to_current("No channel selected".to_string());
+                    return;
+                };
+                let msgid = self.get_current_messages()
+                    .and_then(|msgs| msgs.messages.get(msgs.msg_index))
+                    .and_then(|msg| msg.msgid.clone());
+                let Some(msgid) = msgid else {
+                    self.push_system_to_current("✖ The selected message has no id to reply to".to_string());
+                    return;
+                };
+                irc_tx.send(IrcCommand::Reply { channel, msgid: msgid.clone(), text: text.to_string() }).ok();
+                self.echo_own_msg_tagged(text, MessageTags { reply_to: Some(msgid), ..Default::default() });
+            }
+            s if s == "topic" || s.starts_with("topic ") => {
+                let Some((server_name, channel_name)) = self.get_current_channel_key() else {
+                    self.push_system_to_current("No channel selected".to_string());
+                    return;
+                };
+                if !self.isupport.is_channel(&channel_name) {
+                    self.push_system_to_current("topic only works in a channel".to_string());
+                    return;
+                }
+                let topic = s["topic".len()..].trim();
+                if topic.is_empty() {
+                    self.show_topic(&server_name, &channel_name);
+                    return;
+                }
+                if let Some(max) = self.isupport.topiclen && topic.len() > max {
+                    self.push_system_to_current(format!("✖ Topic is longer than the server allows ({} characters)", max));
+                    return;
+                }
+                irc_tx.send(IrcCommand::Topic { channel: channel_name, topic: topic.to_string() }).ok();
+            }
+            s if s == "cycle" || s.starts_with("cycle ") => {
+                if !self.is_connected {
+                    self.push_system_to_current("Not connected.".to_string());
+                    return;
+                }
+                let named = s["cycle".len()..].trim();
+                let channel = if named.is_empty() { self.channel.clone() } else { named.to_string() };
+                if !self.isupport.is_channel(&channel) {
+                    self.push_system_to_current("Usage: cycle [#channel]".to_string());
+                    return;
+                }
+
+                // The JOIN also asks for NAMES, so the clients list is rebuilt after the rejoin
+                irc_tx.send(IrcCommand::Part(channel.clone())).ok();
+                irc_tx.send(IrcCommand::Join { channel: channel.clone(), key: self.join_key(&channel) }).ok();
+                if channel != self.channel && !self.channel.is_empty() {
+                    irc_tx.send(IrcCommand::SetCurrentChannel(self.channel.clone())).ok();
+                }
+                self.push_system_to_current(format!("Cycling {}...", channel));
+            }
             s if s.starts_with("join") => {
                 if !self.is_connected {
                     self.push_system_to_current("Not connected to server yet. Use 'connect <server>' first.".to_string());
@@ -704,14 +2265,16 @@ impl App {
                 
                 let parts: Vec<&str> = s.splitn(2, ' ').collect();
                 if parts.len() < 2 {
-                    self.push_system_to_current("Usage: join <#channel>".to_string());
+                    self.push_system_to_current("Usage: join <#channel> [key]".to_string());
                     self.push_system_to_current("Example: join #rust".to_string());
                     return;
                 }
                 
-                let channel = parts[1].trim();
-                if channel.is_empty() || !channel.starts_with('#') {
-                    self.push_system_to_current("Channel must start with #".to_string());
+                let mut args = parts[1].split_whitespace();
+                let channel = args.next().unwrap_or("");
+                let key = args.next().map(str::to_string);
+                if let Err(e) = is_valid_channel_name(channel, &self.isupport) {
+                    self.push_system_to_current(format!("✖ {}", e));
                     return;
                 }
                 
@@ -722,19 +2285,19 @@ impl App {
                     return;
                 };
                 
-                self.current_channel = Some(ChannelContext {
+                self.set_current_channel(Some(ChannelContext {
                     server_name: current_server_name.clone(),
                     channel_name: channel.to_string(),
-                });
+                }));
                 
                 self.channel_messages
                     .entry((current_server_name.clone(), channel.to_string()))
                     .or_default();
                 
                 self.channel = channel.to_string();
+                self.remember_join_key(&current_server_name, channel, key.clone());
                 
-                
-                irc_tx.send(IrcCommand::Join(channel.to_string())).ok();
+                irc_tx.send(IrcCommand::Join { channel: channel.to_string(), key }).ok();
                 irc_tx.send(IrcCommand::SetCurrentChannel(channel.to_string())).ok();
                 
             }
@@ -772,6 +2335,7 @@ impl App {
                             client_count: Some(1),
                             is_joined: true,
                             is_dm: true,
+                            ..Default::default()
                         });
                     }
 
@@ -781,23 +2345,26 @@ impl App {
                         .or_default();
 
                     // Switch current buffer
-                    self.current_channel = Some(ChannelContext {
+                    self.set_current_channel(Some(ChannelContext {
                         server_name: server_name.clone(),
                         channel_name: target_user.to_string(),
-                    });
+                    }));
                     self.channel = target_user.to_string();
 
                     // Now push message
-                    let nick = self.current_nick.clone();
-                    self.push_user_msg_to_current(nick.as_str(), message.as_str());
+                    self.echo_own_msg(&message);
                 }
 
                 // Send the message
-                irc_tx.send(IrcCommand::Join(target_user.to_string())).ok();
+                irc_tx.send(IrcCommand::Join { channel: target_user.to_string(), key: None }).ok();
                 irc_tx.send(IrcCommand::PrivMsg(message.clone())).ok();
                 irc_tx.send(IrcCommand::SetCurrentChannel(target_user.to_string())).ok();
                 self.rebuild_server_tree();
             }
+            "reload_config" | "reload" => {
+                irc_tx.send(IrcCommand::ReloadConfig).ok();
+                self.reload_config();
+            }
             "servers" | "list_servers" => {
                 irc_tx.send(IrcCommand::ListServers).ok();
             }
@@ -840,12 +2407,50 @@ impl App {
                 let name = parts[1].trim().to_string();
                 irc_tx.send(IrcCommand::RemoveServer(name)).ok();
             }
+            s if s == "edit_server" || s.starts_with("edit_server ") => {
+                // Format: edit_server <name> <field> <value>
+                let parts: Vec<&str> = s.split_whitespace().collect();
+                if parts.len() < 4 {
+                    self.push_system_to_current("Usage: edit_server <name> <name|address|port|tls> <value>".to_string());
+                    self.push_system_to_current("Example: edit_server MyServer port 6667".to_string());
+                    return;
+                }
+
+                irc_tx.send(IrcCommand::EditServer {
+                    name: parts[1].to_string(),
+                    field: parts[2].to_string(),
+                    value: parts[3].to_string(),
+                }).ok();
+            }
+            s if s.starts_with("raw ") || s == "raw" => {
+                let line = s["raw".len()..].trim();
+                if line.is_empty() {
+                    self.push_system_to_current("Usage: raw <line>".to_string());
+                    self.push_system_to_current("Example: raw WHOIS Alice".to_string());
+                    return;
+                }
+                if !self.is_connected {
+                    self.push_system_to_current("Not connected.".to_string());
+                    return;
+                }
+                irc_tx.send(IrcCommand::Raw(line.to_string())).ok();
+            }
+            "caps" => {
+                if !self.is_connected {
+                    self.push_system_to_current("Not connected.".to_string());
+                    return;
+                }
+                irc_tx.send(IrcCommand::ListCaps).ok();
+            }
+            "whoami" => {
+                if !self.is_connected {
+                    self.push_system_to_current("Not connected.".to_string());
+                    return;
+                }
+                irc_tx.send(IrcCommand::WhoAmI).ok();
+            }
             "status" => {
-                let status = if self.is_connected {
-                    "Connected"
-                } else {
-                    "Disconnected"
-                };
+                let status = self.connection_state.as_str();
                 let channel_status = if self.channel.is_empty() {
                     "No channel joined"
                 } else {
@@ -853,6 +2458,19 @@ impl App {
                 };
                 self.push_system_to_current(format!("Status: {}", status));
                 self.push_system_to_current(format!("Channel: {}", channel_status));
+                if self.connection_state == ConnectionState::Connected {
+                    let limit = |l: Option<usize>| l.map_or("none".to_string(), |l| l.to_string());
+                    let prefixes: String = self.isupport.prefix_symbols().into_iter().collect();
+                    self.push_system_to_current(format!(
+                        "Server limits: nick {}, channel {}, topic {}, modes {}, channel types {}, prefixes {}",
+                        limit(self.isupport.nicklen),
+                        limit(self.isupport.channellen),
+                        limit(self.isupport.topiclen),
+                        limit(self.isupport.modes),
+                        self.isupport.chantypes,
+                        prefixes,
+                    ));
+                }
             }
             "" => {
                 // Empty command, do nothing
@@ -877,9 +2495,49 @@ impl App {
     }
     pub fn execute_normal(&mut self) {
         let norm = self.get_norm_text();
+
+        // Leading digits are a vim style count, e.g. 5| or 3w. A lone 0 is the motion.
+        let digits = norm.find(|c: char| !c.is_ascii_digit()).unwrap_or(norm.len());
+        let (count_str, cmd) = norm.split_at(digits);
+        if let Ok(count) = count_str.parse::<usize>() && !count_str.starts_with('0') {
+            match cmd {
+                "" => {
+                    // still typing the count
+                    return;
+                }
+                "|" => self.move_msg_cursor_to_column(count),
+                "r" => {
+                    // waiting for the replacement character
+                    return;
+                }
+                r if r.starts_with('r') && r.chars().count() == 2 => {
+                    let c = r.chars().nth(1).unwrap_or_default();
+                    self.apply_change(Change::Replace { c, count });
+                }
+                "h" | "l" | "w" | "W" | "b" | "B" | "e" | "E" => {
+                    for _ in 0..count {
+                        self.norm = cmd.to_string();
+                        self.execute_normal();
+                    }
+                }
+                _ => {}
+            }
+            self.clear_norm();
+            return;
+        }
+
         match norm.as_str() {
+            "0" | "|" => {
+                self.move_msg_cursor_to_start();
+                self.clear_norm();
+            }
+            "$" => {
+                self.move_msg_cursor_to_end();
+                self.move_msg_cursor_left();
+                self.clear_norm();
+            }
             "dd" => {
-                self.clear_msg();
+                self.apply_change(Change::DeleteLine);
                 self.clear_norm();
             }
             "gg" => {
@@ -887,7 +2545,13 @@ impl App {
                 self.clear_norm();
             }
             "diw" => {
-                self.delete_inner_word_msg();
+                self.apply_change(Change::DeleteInnerWord);
+                self.clear_norm();
+            }
+            "." => {
+                if let Some(change) = self.last_change.clone() {
+                    self.apply_change(change);
+                }
                 self.clear_norm();
             }
             "G" => {
@@ -895,12 +2559,14 @@ impl App {
                 self.clear_norm();
             }
             "C" => {
-                self.clear_messages();
+                self.clear_view();
                 self.clear_norm();
             }
             "a" => {
+                // Insert first, Normal mode won't let the cursor past the last character
                 self.vim_mode = VimMode::Insert;
                 self.prev_mode = Some(VimMode::Normal);
+                self.move_msg_cursor_right();
                 self.clear_norm();
             }
             "b" => {
@@ -933,8 +2599,20 @@ impl App {
                 self.prev_mode = Some(VimMode::Normal);
                 self.clear_norm();
             }
+            "R" => {
+                self.overwrite = true;
+                self.vim_mode = VimMode::Insert;
+                self.prev_mode = Some(VimMode::Normal);
+                self.clear_norm();
+            }
+            r if r.starts_with('r') && r.chars().count() == 2 => {
+                // r<char> stays in Normal mode, the cursor doesn't move
+                let c = r.chars().nth(1).unwrap_or_default();
+                self.apply_change(Change::Replace { c, count: 1 });
+                self.clear_norm();
+            }
             "q" => {
-                self.should_quit = true;
+                self.request_quit();
             }
             "h" => {
                 self.move_msg_cursor_left();
@@ -945,7 +2623,11 @@ impl App {
                 self.clear_norm();
             }
             "p" => {
-                self.insert_msg_str(self.yank.clone().as_str());
+                self.apply_change(Change::Put);
+                self.clear_norm();
+            }
+            "\"+p" => {
+                self.paste_clipboard();
                 self.clear_norm();
             }
             "s" => {
@@ -976,16 +2658,44 @@ impl App {
                 self.prev_mode = Some(VimMode::Normal);
                 self.clear_norm();
             }
+            "?" => {
+                self.prev_mode = Some(VimMode::Normal);
+                self.open_help();
+                self.clear_norm();
+            }
             _ => {
             }
         }
     }
 
+    // Run an input edit and remember it for .
+    pub fn apply_change(&mut self, change: Change) {
+        match &change {
+            Change::DeleteLine => self.clear_msg(),
+            Change::DeleteInnerWord => self.delete_inner_word_msg(),
+            Change::Put => self.insert_msg_str(self.yank.clone().as_str()),
+            Change::Replace { c, count } => {
+                // 3rx replaces three characters, or nothing when the line is too short
+                if self.msg_cursor + count <= self.msg.len() {
+                    for _ in 0..*count {
+                        self.replace_msg_char(*c);
+                        self.msg_cursor += 1;
+                    }
+                    self.msg_cursor -= 1;
+                }
+            }
+        }
+        self.last_change = Some(change);
+    }
+
     pub fn get_avaiable_normal_commands(&self) -> Vec<&'static str> {
         match self.get_norm_text().as_str() {
             "d" => vec!["d -> delete msg", "i -> delete inner"],
             "di" => vec!["w -> delete inner word"],
             "g" => vec!["gg -> go to start of msg"],
+            "r" => vec!["<char> -> replace character under cursor"],
+            "\"" => vec!["+p -> paste clipboard"],
+            "\"+" => vec!["p -> paste clipboard"],
             _ => vec![],
         }
     }
@@ -1116,7 +2826,145 @@ impl App {
             server.is_expanded = !server.is_expanded;
         }
     }
+    // Enter/double-click on a tree row: toggle a server's connection or open a channel
+    pub fn activate_server_tree_item(&mut self, tree_index: usize, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        let Some(item) = self.server_tree.get(tree_index).copied() else {
+            return;
+        };
+
+        match item {
+            ServerTreeItem::Server { server_idx } => {
+                let server_name = self.servers[server_idx].name.clone();
+
+                if self.is_server_connected(server_idx) {
+                    irc_tx.send(IrcCommand::Disconnect).ok();
+                    self.show_status_buffer(&server_name);
+                    self.push_system_to_current(format!("Disconnecting from {}...", server_name));
+                } else {
+                    // Disconnect any currently connected server
+                    irc_tx.send(IrcCommand::Disconnect).ok();
+
+                    // Connect to this server
+                    irc_tx.send(IrcCommand::Connect(server_name.clone())).ok();
+                    self.push_system_to_current(format!("Connecting to {}...", server_name));
+
+                    self.channel_messages
+                        .entry((server_name.clone(), "status".to_string()))
+                        .or_default();
+
+                    self.set_current_channel(Some(ChannelContext {
+                        server_name: server_name.clone(),
+                        channel_name: "status".to_string(),
+                    }));
+                }
+
+                self.toggle_server_expansion(server_idx);
+            }
+            ServerTreeItem::DmHeader { .. } => {}
+            ServerTreeItem::Channel { server_idx, channel_idx } => {
+                let server_name = self.servers[server_idx].name.clone();
+                let channel_name = self.servers[server_idx].channels[channel_idx].name.clone();
+                let is_dm = self.servers[server_idx].channels[channel_idx].is_dm;
+                let key = self.servers[server_idx].channels[channel_idx].key.clone();
+
+                // Auto-join the channel if connected to server, a DM only needs selecting
+                if self.is_server_connected(server_idx) {
+                    if !is_dm {
+                        irc_tx.send(IrcCommand::Join { channel: channel_name.clone(), key }).ok();
+                    }
+
+                    // Initialize messages for this channel if needed
+                    self.channel_messages
+                        .entry((server_name.clone(), channel_name.clone()))
+                        .or_default();
+
+                    self.set_current_channel(Some(ChannelContext {
+                        server_name,
+                        channel_name: channel_name.clone(),
+                    }));
+
+                    irc_tx.send(IrcCommand::SetCurrentChannel(channel_name.clone())).ok();
+                    self.channel = channel_name;
+                } else {
+                    self.push_system_to_current(format!(
+                        "Not connected to server {}. Connect first.",
+                        server_name
+                    ));
+                }
+            }
+        }
+    }
+
+    // Joined channels and DMs of the connected server, in tree order
+    pub fn joined_channels(&self) -> Vec<ChannelContext> {
+        self.servers
+            .iter()
+            .filter(|server| server.is_connected)
+            .flat_map(|server| {
+                let (dms, channels): (Vec<_>, Vec<_>) = server.channels.iter().partition(|c| c.is_dm);
+                channels
+                    .into_iter()
+                    .chain(dms)
+                    .filter(|c| c.is_joined)
+                    .map(|c| ChannelContext {
+                        server_name: server.name.clone(),
+                        channel_name: c.name.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // Show another channel's buffer, the IRC task answers SetCurrentChannel with its members
+    pub fn switch_to_channel(&mut self, ctx: ChannelContext, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        // The IRC task only has the one connection, the channel name alone would land on it
+        if !self.servers.iter().any(|s| s.name == ctx.server_name && s.is_connected) {
+            self.push_system_to_current(format!("Not connected to server {}. Connect first.", ctx.server_name));
+            return;
+        }
+        let is_dm = self.servers
+            .iter()
+            .filter(|s| s.name == ctx.server_name)
+            .flat_map(|s| s.channels.iter())
+            .any(|c| c.name == ctx.channel_name && c.is_dm);
+
+        self.client_index = 0;
+        self.client_filter = None;
+        if is_dm {
+            self.set_clients(vec![(None, ctx.channel_name.clone())]);
+        } else {
+            self.set_clients(Vec::new());
+        }
+        irc_tx.send(IrcCommand::SetCurrentChannel(ctx.channel_name.clone())).ok();
+        self.channel = ctx.channel_name.clone();
+        self.set_current_channel(Some(ctx));
+    }
+
+    // Alt+Left/Alt+Right: step through joined channels, wrapping around
+    pub fn cycle_channel(&mut self, forward: bool, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
+        let channels = self.joined_channels();
+        if channels.is_empty() {
+            return;
+        }
+        let current = self.current_channel.as_ref().and_then(|cur| {
+            channels
+                .iter()
+                .position(|c| c.server_name == cur.server_name && c.channel_name == cur.channel_name)
+        });
+        let next = match (current, forward) {
+            (Some(i), true) => (i + 1) % channels.len(),
+            (Some(i), false) => (i + channels.len() - 1) % channels.len(),
+            (None, true) => 0,
+            (None, false) => channels.len() - 1,
+        };
+        self.switch_to_channel(channels[next].clone(), irc_tx);
+    }
+
     pub fn rebuild_server_tree(&mut self) {
+        if let Some(filter) = self.server_filter.clone() && !filter.is_empty() {
+            self.rebuild_server_tree_filtered(&filter);
+            return;
+        }
         self.server_tree.clear();
 
         for (s_idx, server) in self.servers.iter().enumerate() {
@@ -1125,12 +2973,8 @@ impl App {
             });
 
             if server.is_expanded {
-                for (c_idx, _) in server.channels.iter().enumerate() {
-                    self.server_tree.push(ServerTreeItem::Channel {
-                        server_idx: s_idx,
-                        channel_idx: c_idx,
-                    });
-                }
+                let channels: Vec<usize> = (0..server.channels.len()).collect();
+                push_channel_rows(&mut self.server_tree, s_idx, server, channels);
             }
         }
 
@@ -1140,6 +2984,59 @@ impl App {
         }
     }
 
+    // Keep servers and channels whose name contains filter. Matching channels show even under
+    // a collapsed server, a matching server shows its channels only when expanded.
+    pub fn rebuild_server_tree_filtered(&mut self, filter: &str) {
+        let filter = filter.to_lowercase();
+        self.server_tree.clear();
+
+        for (s_idx, server) in self.servers.iter().enumerate() {
+            let server_matches = server.name.to_lowercase().contains(&filter);
+            let channels: Vec<usize> = server.channels
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| (server_matches && server.is_expanded) || c.name.to_lowercase().contains(&filter))
+                .map(|(c_idx, _)| c_idx)
+                .collect();
+            if !server_matches && channels.is_empty() {
+                continue;
+            }
+
+            self.server_tree.push(ServerTreeItem::Server {
+                server_idx: s_idx,
+            });
+            push_channel_rows(&mut self.server_tree, s_idx, server, channels);
+        }
+
+        if self.server_tree_index >= self.server_tree.len() {
+            self.server_tree_index = self.server_tree.len().saturating_sub(1);
+        }
+    }
+
+    pub fn start_server_filter(&mut self) {
+        self.server_filter = Some(String::new());
+    }
+
+    pub fn push_server_filter(&mut self, c: char) {
+        if let Some(filter) = &mut self.server_filter {
+            filter.push(c);
+            self.server_tree_index = 0;
+            self.rebuild_server_tree();
+        }
+    }
+
+    pub fn pop_server_filter(&mut self) {
+        if let Some(filter) = &mut self.server_filter {
+            filter.pop();
+            self.rebuild_server_tree();
+        }
+    }
+
+    pub fn clear_server_filter(&mut self) {
+        self.server_filter = None;
+        self.rebuild_server_tree();
+    }
+
     // ----------------- Message Buffer Methods ----------------
     pub fn move_msg_to_index(&mut self, index: usize) {
         if let Some(msgs) = self.get_current_messages_mut() && index < msgs.messages.len() {
@@ -1150,6 +3047,23 @@ impl App {
             } else if msgs.msg_index >= msgs.msg_scroll + msgs.viewport_height {
                 msgs.msg_scroll = msgs.msg_index.saturating_sub(msgs.viewport_height - 1);
             }
+            msgs.update_stick_to_bottom();
+        }
+    }
+
+    // Clamp every buffer's scroll to a new viewport so nothing points past the bottom
+    pub fn resize_viewport(&mut self, viewport_height: usize) {
+        for msgs in self.channel_messages.values_mut() {
+            let len = msgs.messages.len();
+            msgs.viewport_height = viewport_height;
+            msgs.msg_index = msgs.msg_index.min(len.saturating_sub(1));
+            msgs.msg_scroll = msgs.msg_scroll.min(len.saturating_sub(viewport_height));
+            if msgs.stick_to_bottom {
+                msgs.msg_scroll = msgs.bottom_scroll();
+            }
+            if viewport_height > 0 && msgs.msg_index >= msgs.msg_scroll + viewport_height {
+                msgs.msg_scroll = msgs.msg_index + 1 - viewport_height;
+            }
         }
     }
 
@@ -1167,6 +3081,7 @@ impl App {
             if msgs.msg_index < msgs.msg_scroll {
                 msgs.msg_scroll = msgs.msg_index;
             }
+            msgs.update_stick_to_bottom();
         }
     }
 
@@ -1179,6 +3094,40 @@ impl App {
             if msgs.msg_index >= msgs.msg_scroll + msgs.viewport_height {
                 msgs.msg_scroll = msgs.msg_index.saturating_sub(msgs.viewport_height - 1);
             }
+            msgs.update_stick_to_bottom();
+        }
+    }
+
+    // PageUp / PageDown, page_scroll_lines or a full screen
+    pub fn page_size(&self) -> usize {
+        match self.config.ui.page_scroll_lines {
+            0 => self.get_current_messages().map_or(1, |msgs| msgs.viewport_height.max(1)),
+            lines => lines,
+        }
+    }
+
+    pub fn half_page_size(&self) -> usize {
+        self.get_current_messages().map_or(1, |msgs| (msgs.viewport_height / 2).max(1))
+    }
+
+    // Move the view and the selection together, so the selected message keeps its row
+    pub fn scroll_msgs_up(&mut self, lines: usize) {
+        if let Some(msgs) = self.get_current_messages_mut() {
+            msgs.msg_index = msgs.msg_index.saturating_sub(lines);
+            msgs.msg_scroll = msgs.msg_scroll.saturating_sub(lines).min(msgs.msg_index);
+            msgs.update_stick_to_bottom();
+        }
+    }
+
+    pub fn scroll_msgs_down(&mut self, lines: usize) {
+        if let Some(msgs) = self.get_current_messages_mut() && !msgs.messages.is_empty() {
+            let last = msgs.messages.len() - 1;
+            msgs.msg_index = (msgs.msg_index + lines).min(last);
+            msgs.msg_scroll = (msgs.msg_scroll + lines).min(msgs.bottom_scroll());
+            if msgs.msg_index >= msgs.msg_scroll + msgs.viewport_height {
+                msgs.msg_scroll = msgs.msg_index.saturating_sub(msgs.viewport_height.saturating_sub(1));
+            }
+            msgs.update_stick_to_bottom();
         }
     }
 
@@ -1186,6 +3135,7 @@ impl App {
         if let Some(msgs) = self.get_current_messages_mut() {
             msgs.msg_index = 0;
             msgs.msg_scroll = 0;
+            msgs.update_stick_to_bottom();
         }
     }
 
@@ -1195,10 +3145,30 @@ impl App {
                 return;
             }
             msgs.msg_index = msgs.messages.len() - 1;
-            msgs.msg_scroll = msgs.messages.len().saturating_sub(msgs.viewport_height);
+            msgs.msg_scroll = msgs.bottom_scroll();
+            msgs.stick_to_bottom = true;
+        }
+    }
+
+
+    // Sideways scrolling only makes sense when lines aren't wrapped
+    pub fn scroll_msg_left(&mut self, columns: usize) {
+        self.msg_hscroll = self.msg_hscroll.saturating_sub(columns * 8);
+    }
+
+    pub fn scroll_msg_right(&mut self, columns: usize) {
+        if self.config.ui.wrap == WrapMode::Off {
+            self.msg_hscroll += columns * 8;
         }
     }
 
+    // Jump to the nth message (1-based like vim's line numbers), clamped to the buffer
+    pub fn msg_jump_to_number(&mut self, n: usize) {
+        if let Some(msgs) = self.get_current_messages() && !msgs.messages.is_empty() {
+            let index = n.saturating_sub(1).min(msgs.messages.len() - 1);
+            self.move_msg_to_index(index);
+        }
+    }
 
     pub fn yank_msg(&mut self) {
         if let Some(msgs) = self.get_current_messages() && let Some(message) = msgs.messages.get(msgs.msg_index) {
@@ -1206,6 +3176,48 @@ impl App {
         }
     }
 
+    // Like yank_msg but with time and nick, for quoting a conversation elsewhere
+    pub fn yank_msg_full(&mut self) {
+        if let Some(msgs) = self.get_current_messages() && let Some(message) = msgs.messages.get(msgs.msg_index) {
+            self.set_yank(message.quoted_line());
+        }
+    }
+
+    pub fn toggle_msg_line_selection(&mut self) {
+        self.msg_sel_start = match self.msg_sel_start {
+            Some(_) => None,
+            None => self.get_current_messages().map(|msgs| msgs.msg_index),
+        };
+    }
+
+    // First and last message of the V selection, in buffer order
+    pub fn msg_line_selection(&self) -> Option<(usize, usize)> {
+        let start = self.msg_sel_start?;
+        let index = self.get_current_messages()?.msg_index;
+        Some((start.min(index), start.max(index)))
+    }
+
+    // Copy the selected messages as they read in a log, one per line, with_time adds [HH:MM]
+    pub fn yank_msg_selection(&mut self, with_time: bool) {
+        let Some((first, last)) = self.msg_line_selection() else {
+            return;
+        };
+        if let Some(msgs) = self.get_current_messages() {
+            let lines: Vec<String> = msgs.messages
+                .iter()
+                .skip(first)
+                .take(last + 1 - first)
+                .map(|message| match (&message.nick, with_time) {
+                    (_, true) => message.quoted_line(),
+                    (Some(nick), false) => format!("<{}> {}", nick, message.text),
+                    (None, false) => message.text.clone(),
+                })
+                .collect();
+            self.set_yank(lines.join("\n"));
+        }
+        self.msg_sel_start = None;
+    }
+
     pub fn push_char_to_messages_cmd(&mut self, c: char) {
         self.messages_cmd.push(c);
     }
@@ -1215,11 +3227,30 @@ impl App {
     }
 
     pub fn execute_messages_cmd(&mut self) {
-        let cmd = self.messages_cmd.as_str();
-        match cmd {
+        // Leading digits are a vim style count, e.g. 42G or 5j
+        let digits = self.messages_cmd
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.messages_cmd.len());
+        let (count_str, cmd) = self.messages_cmd.split_at(digits);
+        if count_str.starts_with('0') {
+            self.clear_messages_cmd();
+            return;
+        }
+        let count: Option<usize> = count_str.parse().ok();
+        let cmd = cmd.to_string();
+
+        match cmd.as_str() {
+            "" => {
+                // still typing the count
+            }
             "q" => {
                 self.vim_mode = VimMode::Normal;
                 self.prev_mode = Some(VimMode::Messages);
+                self.msg_sel_start = None;
+                self.clear_messages_cmd();
+            }
+            "V" => {
+                self.toggle_msg_line_selection();
                 self.clear_messages_cmd();
             }
             "gg" => {
@@ -1227,11 +3258,28 @@ impl App {
                 self.clear_messages_cmd();
             }
             "G" => {
-                self.msg_jump_bottom();
+                match count {
+                    Some(n) => self.msg_jump_to_number(n),
+                    None => self.msg_jump_bottom(),
+                }
                 self.clear_messages_cmd();
             }
             "y" => {
-                self.yank_msg();
+                if self.msg_sel_start.is_some() {
+                    self.yank_msg_selection(false);
+                } else {
+                    self.yank_msg();
+                }
+                self.vim_mode = VimMode::Normal;
+                self.prev_mode = Some(VimMode::Messages);
+                self.clear_messages_cmd();
+            }
+            "Y" => {
+                if self.msg_sel_start.is_some() {
+                    self.yank_msg_selection(true);
+                } else {
+                    self.yank_msg_full();
+                }
                 self.vim_mode = VimMode::Normal;
                 self.prev_mode = Some(VimMode::Messages);
                 self.clear_messages_cmd();
@@ -1239,33 +3287,152 @@ impl App {
             ":" => {
                 self.vim_mode = VimMode::Command;
                 self.prev_mode = Some(VimMode::Messages);
+                self.msg_sel_start = None;
                 self.clear_messages_cmd();
             }
             "s" => {
                 self.vim_mode = VimMode::Server;
                 self.prev_mode = Some(VimMode::Messages);
+                self.msg_sel_start = None;
                 self.rebuild_server_tree();
                 self.server_tree_index = 0;
                 self.clear_messages_cmd();
             }
             "j" => {
-                self.move_msg_down();
+                for _ in 0..count.unwrap_or(1) {
+                    self.move_msg_down();
+                }
+                self.clear_messages_cmd();
+            }
+            "h" => {
+                self.scroll_msg_left(count.unwrap_or(1));
+                self.clear_messages_cmd();
+            }
+            "l" => {
+                self.scroll_msg_right(count.unwrap_or(1));
                 self.clear_messages_cmd();
             }
             "k" => {
-                self.move_msg_up();
+                for _ in 0..count.unwrap_or(1) {
+                    self.move_msg_up();
+                }
                 self.clear_messages_cmd();
             }
             "c" => {
                 self.vim_mode = VimMode::Clients;
                 self.prev_mode = Some(VimMode::Messages);
+                self.msg_sel_start = None;
                 self.clear_messages_cmd();
             }
+            "g" => {
+                // waiting for the second g
+            }
             _ => {
+                self.clear_messages_cmd();
             }
         }
     }
 
+    // ----------------- Mouse Selection Methods ----------------
+    pub fn start_mouse_selection(&mut self, x: u16, y: u16) {
+        self.mouse_sel = if self.msg_area.contains(Position::new(x, y)) {
+            Some(((x, y), (x, y)))
+        } else {
+            None
+        };
+    }
+
+    pub fn update_mouse_selection(&mut self, x: u16, y: u16) {
+        let area = self.msg_area;
+        if let Some((anchor, head)) = &mut self.mouse_sel && !area.is_empty() {
+            // The anchor too, the area may have shrunk since the drag started
+            *anchor = clamp_to(area, *anchor);
+            *head = clamp_to(area, (x, y));
+        }
+    }
+
+    // Selected cells as (start, end) in reading order, end inclusive
+    pub fn mouse_selection_cells(&self) -> Option<((u16, u16), (u16, u16))> {
+        let (anchor, head) = self.mouse_sel?;
+        if (anchor.1, anchor.0) <= (head.1, head.0) {
+            Some((anchor, head))
+        } else {
+            Some((head, anchor))
+        }
+    }
+
+    pub fn finish_mouse_selection(&mut self) {
+        let Some((start, end)) = self.mouse_selection_cells() else {
+            return;
+        };
+        self.mouse_sel = None;
+        let area = self.msg_area;
+        if start == end || area.is_empty() {
+            // plain click, nothing was dragged
+            return;
+        }
+        let (start, end) = (clamp_to(area, start), clamp_to(area, end));
+
+        let mut lines = Vec::new();
+        for y in start.1..=end.1 {
+            let Some(row) = self.msg_rows.get((y - area.y) as usize) else {
+                continue;
+            };
+            let from = if y == start.1 { start.0 - area.x } else { 0 } as usize;
+            let to = if y == end.1 { end.0 - area.x + 1 } else { area.width } as usize;
+            let line: String = row.chars().skip(from).take(to.saturating_sub(from)).collect();
+            lines.push(line.trim_end().to_string());
+        }
+
+        let text = lines.join("\n");
+        if !text.trim().is_empty() {
+            self.set_yank(text);
+        }
+    }
+
+    pub fn url_at(&self, x: u16, y: u16) -> Option<&str> {
+        self.msg_urls
+            .iter()
+            .find(|span| span.y == y && x >= span.x_start && x < span.x_end)
+            .map(|span| span.url.as_str())
+    }
+
+    // Width of the server tree panel, borders included
+    pub fn tree_width(&self) -> u16 {
+        if self.config.ui.tree_width > 0 {
+            return self.config.ui.tree_width;
+        }
+        self.servers
+            .iter()
+            .flat_map(|s| std::iter::once(s.name.len()).chain(s.channels.iter().map(|c| c.name.len())))
+            .max()
+            .unwrap_or(0) as u16
+            + 10
+    }
+
+    pub fn clients_width(&self) -> u16 {
+        self.config.ui.clients_width
+    }
+
+    // < and > in Server / Clients mode, a fitted tree starts from its current width
+    pub fn resize_panel(&mut self, tree: bool, grow: bool) {
+        let width = if tree { self.tree_width() } else { self.clients_width() };
+        let width = if grow { width + PANEL_RESIZE_STEP } else { width.saturating_sub(PANEL_RESIZE_STEP).max(MIN_PANEL_WIDTH) };
+        if tree {
+            self.config.ui.tree_width = width;
+        } else {
+            self.config.ui.clients_width = width;
+        }
+        self.save_config();
+    }
+
+    pub fn tab_at(&self, x: u16, y: u16) -> Option<ChannelContext> {
+        self.channel_tabs
+            .iter()
+            .find(|tab| tab.y == y && x >= tab.x_start && x < tab.x_end)
+            .map(|tab| tab.ctx.clone())
+    }
+
     // ----------------- Client Buffer Methods ----------------
     pub fn move_client_selection_up(&mut self) {
         if self.client_index > 0 {
@@ -1274,13 +3441,70 @@ impl App {
     }
 
     pub fn move_client_selection_down(&mut self) {
-        if self.client_index + 1 < self.clients.len() {
+        if self.client_index + 1 < self.client_view.len() {
             self.client_index += 1;
         }
     }
 
+    // Replace the member list, the IRC task already sorted it ops first
+    pub fn set_clients(&mut self, clients: Vec<(Option<char>, String)>) {
+        self.clients = clients
+            .into_iter()
+            .map(|(prefix, name)| ClientInfo { name, prefix })
+            .collect();
+        self.rebuild_client_view();
+    }
+
+    // Keep the clients whose nick holds the filter's letters in order, "jdo" finds "john_doe"
+    pub fn rebuild_client_view(&mut self) {
+        let filter = self.client_filter.as_deref().unwrap_or("").to_lowercase();
+        self.client_view = self.clients
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                let name = c.name.to_lowercase();
+                let mut rest = name.chars();
+                filter.chars().all(|f| rest.any(|n| n == f))
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if self.client_index >= self.client_view.len() {
+            self.client_index = self.client_view.len().saturating_sub(1);
+        }
+    }
+
+    // What the clients panel shows, in order
+    pub fn visible_clients(&self) -> impl Iterator<Item = &ClientInfo> {
+        self.client_view.iter().filter_map(|&i| self.clients.get(i))
+    }
+
+    pub fn start_client_filter(&mut self) {
+        self.client_filter = Some(String::new());
+    }
+
+    pub fn push_client_filter(&mut self, c: char) {
+        if let Some(filter) = &mut self.client_filter {
+            filter.push(c);
+            self.client_index = 0;
+            self.rebuild_client_view();
+        }
+    }
+
+    pub fn pop_client_filter(&mut self) {
+        if let Some(filter) = &mut self.client_filter {
+            filter.pop();
+            self.rebuild_client_view();
+        }
+    }
+
+    pub fn clear_client_filter(&mut self) {
+        self.client_filter = None;
+        self.rebuild_client_view();
+    }
+
     pub fn get_selected_client(&self) -> Option<&ClientInfo> {
-        self.clients.get(self.client_index)
+        self.client_view.get(self.client_index).and_then(|&i| self.clients.get(i))
     }
 
     pub fn client_jump_top(&mut self) {
@@ -1288,18 +3512,28 @@ impl App {
     }
 
     pub fn client_jump_bottom(&mut self) {
-        if self.clients.is_empty() {
+        if self.client_view.is_empty() {
             return;
         }
-        self.client_index = self.clients.len() - 1;
+        self.client_index = self.client_view.len() - 1;
     }
 
     pub fn yank_client(&mut self) {
-        if let Some(client) = self.clients.get(self.client_index) {
+        if let Some(client) = self.get_selected_client() {
             self.set_yank(client.name.clone());
         }
     }
 
+    // "nick: " at the cursor, the usual way to address someone in a channel
+    pub fn mention_selected_client(&mut self) {
+        if let Some(client) = self.get_selected_client() {
+            let mention = format!("{}: ", client.name);
+            self.move_msg_cursor_to_start();
+            self.insert_msg_str(&mention);
+            self.move_msg_cursor_to_end();
+        }
+    }
+
     pub fn join_selected_client_channel(&mut self, irc_tx: &tokio::sync::mpsc::UnboundedSender<IrcCommand>) {
         if let Some(client) = self.get_selected_client() {
             if !self.is_connected {
@@ -1321,6 +3555,7 @@ impl App {
                         client_count: None,
                         is_joined: true,
                         is_dm: true,
+                        ..Default::default()
                     });
                 }
                 
@@ -1330,10 +3565,10 @@ impl App {
                 return;
             };
 
-            self.current_channel = Some(ChannelContext {
+            self.set_current_channel(Some(ChannelContext {
                 server_name: current_server_name.clone(),
                 channel_name: channel_name.clone(),
-            });
+            }));
 
             self.channel_messages
                 .entry((current_server_name.clone(), channel_name.clone()))
@@ -1341,13 +3576,13 @@ impl App {
 
             self.channel = channel_name.clone();
 
-            irc_tx.send(IrcCommand::Join(channel_name.clone())).ok();
+            irc_tx.send(IrcCommand::Join { channel: channel_name.clone(), key: None }).ok();
             irc_tx.send(IrcCommand::SetCurrentChannel(channel_name)).ok();
         }
     }
 
     pub fn move_client_to_index(&mut self, index: usize) {
-        if index < self.clients.len() {
+        if index < self.client_view.len() {
             self.client_index = index;
         }
     }
@@ -1386,6 +3621,19 @@ impl App {
                 self.prev_mode = Some(VimMode::Clients);
                 self.clear_clients_cmd();
             }
+            "Y" => {
+                self.yank_client();
+                if let Some(client) = self.get_selected_client() {
+                    self.push_system_to_current(format!("Copied {}", client.name));
+                }
+                self.clear_clients_cmd();
+            }
+            "@" => {
+                self.mention_selected_client();
+                self.vim_mode = VimMode::Insert;
+                self.prev_mode = Some(VimMode::Clients);
+                self.clear_clients_cmd();
+            }
             "j" => {
                 self.move_client_selection_down();
                 self.clear_clients_cmd();
@@ -1421,12 +3669,20 @@ impl App {
         let cmd = self.take_msg_text();
         match cmd.as_str() {
             "/quit" | "/q" => {
-                self.should_quit = true;
+                self.request_quit();
             }
             "/vim" | "/v" => {
                 self.vim_mode = VimMode::Normal;
                 self.prev_mode = None;
             }
+            "/list" => {
+                if !self.is_connected {
+                    self.push_system_to_current("Not connected to server yet. Use '/connect <server>' first.".to_string());
+                    return;
+                }
+                irc_tx.send(IrcCommand::ListChannels).ok();
+                self.open_channel_list();
+            }
             s if s.starts_with("/nick") => {
                 let parts: Vec<&str> = s.splitn(2, ' ').collect();
                 if parts.len() < 2 {
@@ -1434,6 +3690,10 @@ impl App {
                     return;
                 }
                 let nick = parts[1].trim();
+                if let Err(e) = is_valid_nick(nick, &self.isupport) {
+                    self.push_system_to_current(format!("✖ {}", e));
+                    return;
+                }
                 irc_tx.send(IrcCommand::Nick(nick.to_string())).ok();
                 self.current_nick = nick.to_string();
             }
@@ -1474,13 +3734,15 @@ impl App {
                 
                 let parts: Vec<&str> = s.splitn(2, ' ').collect();
                 if parts.len() < 2 {
-                    self.push_system_to_current("Usage: /join <#channel>".to_string());
+                    self.push_system_to_current("Usage: /join <#channel> [key]".to_string());
                     return;
                 }
                 
-                let channel = parts[1].trim();
-                if channel.is_empty() || !channel.starts_with('#') {
-                    self.push_system_to_current("Channel must start with #".to_string());
+                let mut args = parts[1].split_whitespace();
+                let channel = args.next().unwrap_or("");
+                let key = args.next().map(str::to_string);
+                if let Err(e) = is_valid_channel_name(channel, &self.isupport) {
+                    self.push_system_to_current(format!("✖ {}", e));
                     return;
                 }
                 
@@ -1491,19 +3753,19 @@ impl App {
                     return;
                 };
                 
-                self.current_channel = Some(ChannelContext {
+                self.set_current_channel(Some(ChannelContext {
                     server_name: current_server_name.clone(),
                     channel_name: channel.to_string(),
-                });
+                }));
                 
                 self.channel_messages
                     .entry((current_server_name.clone(), channel.to_string()))
                     .or_default();
                 
                 self.channel = channel.to_string();
+                self.remember_join_key(&current_server_name, channel, key.clone());
                 
-                
-                irc_tx.send(IrcCommand::Join(channel.to_string())).ok();
+                irc_tx.send(IrcCommand::Join { channel: channel.to_string(), key }).ok();
                 irc_tx.send(IrcCommand::SetCurrentChannel(channel.to_string())).ok();
                 self.rebuild_server_tree();
                 
@@ -1541,6 +3803,7 @@ impl App {
                             client_count: Some(1),
                             is_joined: true,
                             is_dm: true,
+                            ..Default::default()
                         });
                     }
 
@@ -1550,27 +3813,337 @@ impl App {
                         .or_default();
 
                     // Switch current buffer
-                    self.current_channel = Some(ChannelContext {
+                    self.set_current_channel(Some(ChannelContext {
                         server_name: server_name.clone(),
                         channel_name: target_user.to_string(),
-                    });
+                    }));
                     self.channel = target_user.to_string();
 
                     // Now push message
-                    let nick = self.current_nick.clone();
-                    self.push_user_msg_to_current(nick.as_str(), message.as_str());
+                    self.echo_own_msg(&message);
                 }
 
                 // Send the message
-                irc_tx.send(IrcCommand::Join(target_user.to_string())).ok();
+                irc_tx.send(IrcCommand::Join { channel: target_user.to_string(), key: None }).ok();
                 irc_tx.send(IrcCommand::PrivMsg(message.clone())).ok();
                 irc_tx.send(IrcCommand::SetCurrentChannel(target_user.to_string())).ok();
                 self.rebuild_server_tree();
             }
             _ => {
-                self.push_user_msg_to_current(self.current_nick.clone().as_str(), cmd.as_str());
+                self.echo_own_msg(&cmd);
                 irc_tx.send(IrcCommand::PrivMsg(cmd)).ok();
             }
         }
     }
 }
+
+// The nearest cell inside a non-empty area
+fn clamp_to(area: Rect, (x, y): (u16, u16)) -> (u16, u16) {
+    (x.clamp(area.left(), area.right() - 1), y.clamp(area.top(), area.bottom() - 1))
+}
+
+// Case-insensitive nick match where '*' matches any run of characters and '?' any single one
+fn mask_matches(mask: &str, nick: &str) -> bool {
+    let mask: Vec<char> = mask.to_lowercase().chars().collect();
+    let nick: Vec<char> = nick.to_lowercase().chars().collect();
+
+    let (mut m, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < nick.len() {
+        if m < mask.len() && (mask[m] == '?' || mask[m] == nick[n]) {
+            m += 1;
+            n += 1;
+        } else if m < mask.len() && mask[m] == '*' {
+            backtrack = Some((m, n));
+            m += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // let the last '*' swallow one more character
+            m = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    mask[m..].iter().all(|&c| c == '*')
+}
+
+// Channels first, then the DMs under their own "Direct Messages" row
+fn push_channel_rows(tree: &mut Vec<ServerTreeItem>, server_idx: usize, server: &ServerInfo, channels: Vec<usize>) {
+    let (dms, channels): (Vec<usize>, Vec<usize>) = channels
+        .into_iter()
+        .partition(|&c_idx| server.channels[c_idx].is_dm);
+    for channel_idx in channels {
+        tree.push(ServerTreeItem::Channel { server_idx, channel_idx });
+    }
+    if !dms.is_empty() {
+        tree.push(ServerTreeItem::DmHeader { server_idx });
+        for channel_idx in dms {
+            tree.push(ServerTreeItem::Channel { server_idx, channel_idx });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after(motion: fn(&mut App), text: &str, from: usize) -> usize {
+        let mut app = App::for_test(text, from);
+        motion(&mut app);
+        app.msg_cursor
+    }
+
+    // Words and WORDs both split on whitespace only, so every case holds for either
+    fn assert_motion(lower: fn(&mut App), upper: fn(&mut App), text: &str, from: usize, expected: usize) {
+        assert_eq!(after(lower, text, from), expected, "word motion on {:?} from {}", text, from);
+        assert_eq!(after(upper, text, from), expected, "WORD motion on {:?} from {}", text, from);
+    }
+
+    fn assert_w(text: &str, from: usize, expected: usize) {
+        assert_motion(App::move_msg_cursor_forward_word, App::move_msg_cursor_forward_word_uppercase, text, from, expected);
+    }
+
+    fn assert_b(text: &str, from: usize, expected: usize) {
+        assert_motion(App::move_msg_cursor_back_word, App::move_msg_cursor_back_word_uppercase, text, from, expected);
+    }
+
+    fn assert_e(text: &str, from: usize, expected: usize) {
+        assert_motion(App::move_msg_cursor_end_of_word, App::move_msg_cursor_end_of_word_uppercase, text, from, expected);
+    }
+
+    // The system buffer with a 10 row viewport and count messages in it
+    fn app_with_messages(count: usize) -> App {
+        let mut app = App::for_test("", 0);
+        if let Some(msgs) = app.get_current_messages_mut() {
+            msgs.viewport_height = 10;
+        }
+        for i in 0..count {
+            app.push_system_to_current(format!("message {}", i));
+        }
+        app
+    }
+
+    fn view(app: &mut App) -> (usize, usize, bool) {
+        let msgs = app.get_current_messages_mut().unwrap();
+        (msgs.msg_scroll, msgs.msg_index, msgs.stick_to_bottom)
+    }
+
+    #[test]
+    fn scrolled_up_view_stays_put_when_messages_arrive() {
+        let mut app = app_with_messages(30);
+        app.scroll_msgs_up(12);
+        let (scroll, index, stick) = view(&mut app);
+        assert!(!stick);
+
+        for i in 0..5 {
+            app.push_system_to_current(format!("late {}", i));
+        }
+        assert_eq!(view(&mut app), (scroll, index, false));
+    }
+
+    #[test]
+    fn view_at_the_bottom_follows_new_messages() {
+        let mut app = app_with_messages(30);
+        assert_eq!(view(&mut app), (20, 29, true));
+
+        for i in 0..5 {
+            app.push_system_to_current(format!("late {}", i));
+        }
+        assert_eq!(view(&mut app), (25, 34, true));
+    }
+
+    #[test]
+    fn motions_on_empty_input_stay_put() {
+        assert_w("", 0, 0);
+        assert_b("", 0, 0);
+        assert_e("", 0, 0);
+    }
+
+    #[test]
+    fn motions_on_one_word() {
+        assert_w("hello", 0, 5);
+        assert_b("hello", 5, 0);
+        assert_b("hello", 3, 0);
+        assert_e("hello", 0, 4);
+        assert_e("hello", 4, 4);
+    }
+
+    #[test]
+    fn motions_across_runs_of_whitespace() {
+        assert_w("foo   bar", 0, 6);
+        assert_w("foo   bar", 4, 6);
+        assert_b("foo   bar", 6, 0);
+        assert_b("foo   bar", 4, 0);
+        assert_e("foo   bar", 0, 2);
+        assert_e("foo   bar", 2, 8);
+        assert_e("foo   bar", 4, 8);
+    }
+
+    #[test]
+    fn motions_before_trailing_whitespace() {
+        assert_w("foo   ", 0, 6);
+        assert_b("foo   ", 6, 0);
+        assert_e("foo   ", 0, 2);
+        assert_e("foo   ", 2, 2);
+    }
+
+    #[test]
+    fn punctuation_belongs_to_the_word() {
+        assert_w("foo.bar, baz", 0, 9);
+        assert_b("foo.bar, baz", 9, 0);
+        assert_b("foo.bar, baz", 11, 9);
+        assert_e("foo.bar, baz", 0, 7);
+        assert_e("foo.bar, baz", 7, 11);
+    }
+
+    #[test]
+    fn motions_from_the_last_character() {
+        assert_w("foo bar", 6, 7);
+        assert_b("foo bar", 6, 4);
+        assert_e("foo bar", 6, 6);
+    }
+
+    #[test]
+    fn motions_from_the_end_of_input() {
+        assert_w("foo bar", 7, 7);
+        assert_b("foo bar", 7, 4);
+        assert_e("foo bar", 7, 7);
+    }
+
+    #[test]
+    fn e_from_inside_a_word_stops_at_its_end() {
+        assert_eq!(after(App::move_msg_cursor_end_of_word, "hello world", 1), 4);
+    }
+
+    #[test]
+    fn e_from_the_end_of_a_word_moves_to_the_next() {
+        assert_eq!(after(App::move_msg_cursor_end_of_word, "hello world", 4), 10);
+    }
+
+    // Connected to "net" with nothing joined yet
+    fn connected_app() -> App {
+        let mut app = App::for_test("", 0);
+        app.servers.push(ServerInfo {
+            name: "net".to_string(),
+            is_connected: true,
+            channels: Vec::new(),
+            is_expanded: true,
+            autoconnect: false,
+        });
+        app.is_connected = true;
+        app.last_server = Some("net".to_string());
+        app
+    }
+
+    #[test]
+    fn cycle_and_reconnect_reuse_the_join_key() {
+        let mut app = connected_app();
+        let (irc_tx, mut irc_rx) = tokio::sync::mpsc::unbounded_channel();
+        app.execute_command("join #Secret hunter2", &irc_tx);
+        while irc_rx.try_recv().is_ok() {}
+        // Nothing reaches the tree until the server confirms, a 475 leaves no stray entry
+        assert!(app.servers[0].channels.is_empty());
+
+        // What ChannelUpdate does once NAMES arrives, the server may spell the name its own way
+        let key = app.take_join_key("net", "#secret");
+        app.servers[0].channels.push(ChannelInfo { name: "#secret".to_string(), is_joined: true, key, ..Default::default() });
+        assert!(app.pending_join_keys.is_empty());
+
+        app.execute_command("cycle #secret", &irc_tx);
+        let mut rejoined = false;
+        while let Ok(cmd) = irc_rx.try_recv() {
+            if let IrcCommand::Join { channel, key } = cmd {
+                assert_eq!((channel.as_str(), key.as_deref()), ("#secret", Some("hunter2")));
+                rejoined = true;
+            }
+        }
+        assert!(rejoined);
+
+        app.execute_command("reconnect", &irc_tx);
+        let rejoin = std::iter::from_fn(|| irc_rx.try_recv().ok())
+            .find_map(|cmd| match cmd {
+                IrcCommand::RejoinAll(channels) => Some(channels),
+                _ => None,
+            });
+        assert_eq!(rejoin, Some(vec![("#secret".to_string(), Some("hunter2".to_string()))]));
+    }
+
+    #[test]
+    fn reply_goes_to_the_shown_channel_and_echoes_with_its_parent() {
+        let mut app = connected_app();
+        app.enabled_caps.push("message-tags".to_string());
+        app.set_current_channel(Some(ChannelContext {
+            server_name: "net".to_string(),
+            channel_name: "#duck".to_string(),
+        }));
+        let tags = MessageTags { msgid: Some("abc".to_string()), ..Default::default() };
+        app.push_user_msg_to("net", "#duck", "mallard", "quack?", Local::now(), tags);
+
+        let (irc_tx, mut irc_rx) = tokio::sync::mpsc::unbounded_channel();
+        app.execute_command("reply quack!", &irc_tx);
+        let sent = std::iter::from_fn(|| irc_rx.try_recv().ok()).find(|cmd| matches!(cmd, IrcCommand::Reply { .. }));
+        assert!(matches!(
+            sent,
+            Some(IrcCommand::Reply { channel, msgid, text }) if channel == "#duck" && msgid == "abc" && text == "quack!"
+        ));
+        let echo = app.get_current_messages().and_then(|msgs| msgs.messages.last()).unwrap();
+        assert_eq!(echo.text, "quack!");
+        assert_eq!(echo.reply_to.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn renaming_a_server_moves_its_entry_and_buffers() {
+        let mut app = connected_app();
+        let (irc_tx, _irc_rx) = tokio::sync::mpsc::unbounded_channel();
+        app.execute_command("join #duck", &irc_tx);
+        app.push_system_to_current("quack".to_string());
+
+        app.rename_server("net", "pond");
+        assert_eq!(app.servers[0].name, "pond");
+        assert_eq!(app.last_server.as_deref(), Some("pond"));
+        assert!(!app.channel_messages.keys().any(|(server, _)| server == "net"));
+        let msgs = &app.channel_messages[&("pond".to_string(), "#duck".to_string())];
+        assert_eq!(msgs.messages.last().map(|m| m.text.as_str()), Some("quack"));
+        assert_eq!(app.get_current_channel_key(), Some(("pond".to_string(), "#duck".to_string())));
+    }
+
+    #[test]
+    fn channel_switching_stays_on_the_connected_server() {
+        let mut app = connected_app();
+        app.servers[0].channels.push(ChannelInfo { name: "#duck".to_string(), is_joined: true, ..Default::default() });
+        app.servers.push(ServerInfo {
+            name: "old".to_string(),
+            is_connected: false,
+            channels: vec![ChannelInfo { name: "#stale".to_string(), is_joined: true, unread_count: 3, ..Default::default() }],
+            is_expanded: true,
+            autoconnect: false,
+        });
+        let joined: Vec<String> = app.joined_channels().into_iter().map(|c| c.channel_name).collect();
+        assert_eq!(joined, vec!["#duck"]);
+        assert!(app.next_unread_channel().is_none());
+
+        let (irc_tx, mut irc_rx) = tokio::sync::mpsc::unbounded_channel();
+        app.switch_to_channel(ChannelContext { server_name: "old".to_string(), channel_name: "#stale".to_string() }, &irc_tx);
+        assert!(irc_rx.try_recv().is_err());
+        assert_ne!(app.channel, "#stale");
+    }
+
+    #[test]
+    fn mouse_selection_survives_the_area_shrinking_under_it() {
+        let mut app = App::for_test("", 0);
+        app.msg_area = Rect::new(10, 5, 20, 4);
+        app.msg_rows = vec!["quack".to_string(), "honk".to_string(), String::new(), String::new()];
+        app.start_mouse_selection(10, 5);
+        app.msg_area = Rect::new(12, 6, 20, 3);
+        app.msg_rows = vec!["honk".to_string(), String::new(), String::new()];
+        app.update_mouse_selection(15, 6);
+        app.finish_mouse_selection();
+        assert_eq!(app.yank, "honk");
+
+        // Resized again before the release, nothing clamped the anchor yet
+        app.mouse_sel = Some(((10, 5), (15, 6)));
+        app.finish_mouse_selection();
+        assert_eq!(app.yank, "honk");
+    }
+}
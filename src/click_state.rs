@@ -7,11 +7,11 @@ pub struct ClickState {
 }
 
 impl ClickState {
-    pub fn new() -> Self {
+    pub fn new(double_click_threshold: Duration) -> Self {
         Self {
             last_click_time: None,
             last_click_pos: None,
-            double_click_threshold: Duration::from_millis(500),
+            double_click_threshold,
         }
     }
 
@@ -27,8 +27,14 @@ impl ClickState {
             false
         };
 
-        self.last_click_time = Some(now);
-        self.last_click_pos = Some((x, y));
+        if is_double {
+            // Start fresh so a third click isn't counted as another double-click
+            self.last_click_time = None;
+            self.last_click_pos = None;
+        } else {
+            self.last_click_time = Some(now);
+            self.last_click_pos = Some((x, y));
+        }
         is_double
     }
 }
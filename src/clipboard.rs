@@ -0,0 +1,61 @@
+use anyhow::{Result, bail};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use wl_clipboard_rs::copy::{MimeType, Options, Source};
+use wl_clipboard_rs::paste::{self, ClipboardType, Seat};
+
+// Tried in order when there is no Wayland compositor, (program, args to copy, args to paste)
+const X11_TOOLS: &[(&str, &[&str], &[&str])] = &[
+    ("xclip", &["-selection", "clipboard", "-in"], &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--input"], &["--clipboard", "--output"]),
+];
+
+// Put text on the system clipboard, Wayland first and then whichever X11 tool is installed
+pub fn copy(text: &str) -> Result<()> {
+    let wayland = Options::new().copy(
+        Source::Bytes(text.as_bytes().to_vec().into()),
+        MimeType::Autodetect,
+    );
+    if wayland.is_ok() {
+        return Ok(());
+    }
+    for (program, copy_args, _) in X11_TOOLS {
+        if x11_copy(program, copy_args, text).is_ok() {
+            return Ok(());
+        }
+    }
+    bail!("no Wayland compositor, xclip or xsel to copy with")
+}
+
+// The system clipboard's text, None when it is empty or nothing could read it
+pub fn paste() -> Option<String> {
+    let wayland = paste::get_contents(ClipboardType::Regular, Seat::Unspecified, paste::MimeType::Text)
+        .ok()
+        .and_then(|(mut pipe, _)| {
+            let mut text = String::new();
+            pipe.read_to_string(&mut text).ok().map(|_| text)
+        });
+    wayland.or_else(|| {
+        X11_TOOLS.iter().find_map(|(program, _, paste_args)| {
+            let output = Command::new(program).args(*paste_args).stderr(Stdio::null()).output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+    })
+}
+
+// xclip and xsel fork to keep serving the selection, so this only waits for the parent
+fn x11_copy(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        bail!("{} failed", program);
+    }
+    Ok(())
+}
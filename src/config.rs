@@ -0,0 +1,247 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// How long message lines are laid out in the message pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WrapMode {
+    // wrap and drop leading whitespace on wrapped rows
+    #[default]
+    Trim,
+    // wrap but keep whitespace, for ASCII art and code
+    On,
+    // no wrapping, scroll sideways with h/l in Messages mode
+    Off,
+}
+
+impl WrapMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "trim" => Some(WrapMode::Trim),
+            "on" => Some(WrapMode::On),
+            "off" => Some(WrapMode::Off),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WrapMode::Trim => "trim",
+            WrapMode::On => "on",
+            WrapMode::Off => "off",
+        }
+    }
+}
+
+// The mode duckIRC opens in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StartMode {
+    #[default]
+    Normal,
+    Insert,
+    // for people who don't want vim keys at all
+    Vimless,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    #[serde(default = "default_double_click_ms")]
+    pub double_click_ms: u64,
+    // ring the terminal bell on highlights in other channels
+    #[serde(default = "default_true")]
+    pub bell: bool,
+    // strftime format put in front of each message, empty hides timestamps
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+    // fold runs of join/part/quit lines into one summary line
+    #[serde(default = "default_true")]
+    pub collapse_presence: bool,
+    #[serde(default)]
+    pub wrap: WrapMode,
+    // pastes longer than this ask before sending
+    #[serde(default = "default_paste_confirm_lines")]
+    pub paste_confirm_lines: usize,
+    // put in front of system messages to tell them apart from chat, empty for none
+    #[serde(default = "default_system_prefix")]
+    pub system_prefix: String,
+    // nicks are right-aligned (and cut) to this many characters so messages start in one column, 0 is off
+    #[serde(default)]
+    pub nick_column: usize,
+    // put after a nick completed with Tab at the start of the line, e.g. ": " or ", "
+    #[serde(default = "default_completion_suffix")]
+    pub completion_suffix: String,
+    // a message from the same nick within this many seconds of their last one shows without the nick, 0 is off
+    #[serde(default)]
+    pub group_messages_secs: u64,
+    #[serde(default)]
+    pub default_mode: StartMode,
+    // messages moved by PageUp / PageDown, 0 is a full screen
+    #[serde(default)]
+    pub page_scroll_lines: usize,
+    // briefly turn the borders red when an IRC error comes in
+    #[serde(default = "default_true")]
+    pub flash_on_error: bool,
+    // capture the mouse, off leaves selection to the terminal (handy over ssh or in tmux)
+    #[serde(default = "default_true")]
+    pub mouse: bool,
+    // a row of joined-channel tabs above the messages, for those who'd rather not open the tree
+    #[serde(default)]
+    pub tab_bar: bool,
+    // columns of the server tree, 0 fits it to the longest name
+    #[serde(default)]
+    pub tree_width: u16,
+    #[serde(default = "default_clients_width")]
+    pub clients_width: u16,
+}
+
+fn default_double_click_ms() -> u64 {
+    500
+}
+
+fn default_timestamp_format() -> String {
+    "%H:%M".to_string()
+}
+
+fn default_paste_confirm_lines() -> usize {
+    3
+}
+
+fn default_completion_suffix() -> String {
+    ": ".to_string()
+}
+
+fn default_system_prefix() -> String {
+    "*".to_string()
+}
+
+fn default_clients_width() -> u16 {
+    15
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            double_click_ms: default_double_click_ms(),
+            bell: true,
+            timestamp_format: default_timestamp_format(),
+            collapse_presence: true,
+            wrap: WrapMode::default(),
+            paste_confirm_lines: default_paste_confirm_lines(),
+            system_prefix: default_system_prefix(),
+            nick_column: 0,
+            completion_suffix: default_completion_suffix(),
+            group_messages_secs: 0,
+            default_mode: StartMode::default(),
+            page_scroll_lines: 0,
+            flash_on_error: true,
+            mouse: true,
+            tab_bar: false,
+            tree_width: 0,
+            clients_width: default_clients_width(),
+        }
+    }
+}
+
+fn default_log_max_kb() -> u64 {
+    1024
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    30
+}
+
+fn default_log_preload_lines() -> usize {
+    50
+}
+
+fn default_caps() -> Vec<String> {
+    ["server-time", "multi-prefix", "account-tag", "echo-message", "message-tags", "draft/message-redaction"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    // nicks (or masks like spammer*) whose messages are hidden
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    // words that notify like a mention of our nick
+    #[serde(default)]
+    pub highlights: Vec<String>,
+    // nicks we're told about when they come online or leave (needs MONITOR)
+    #[serde(default)]
+    pub notify: Vec<String>,
+    // IRCv3 capabilities requested when the server offers them
+    #[serde(default = "default_caps")]
+    pub caps: Vec<String>,
+    // append every message to ~/.config/duckIRC/logs/<server>/<channel>.log
+    #[serde(default)]
+    pub logging: bool,
+    // a log bigger than this is moved to <channel>.log.1, 0 never rotates
+    #[serde(default = "default_log_max_kb")]
+    pub log_max_kb: u64,
+    // how many log lines to show when opening a channel, 0 shows none
+    #[serde(default = "default_log_preload_lines")]
+    pub log_preload_lines: usize,
+    // mark us away after this many minutes without a keypress, 0 turns it off
+    #[serde(default)]
+    pub auto_away_minutes: u64,
+    // give up on a server that hasn't accepted the connection after this many seconds
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default)]
+    pub ui: UiConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            ignore: Vec::new(),
+            highlights: Vec::new(),
+            notify: Vec::new(),
+            caps: default_caps(),
+            logging: false,
+            log_max_kb: default_log_max_kb(),
+            log_preload_lines: default_log_preload_lines(),
+            auto_away_minutes: 0,
+            connect_timeout_secs: default_connect_timeout_secs(),
+            ui: UiConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn load(path: &str) -> Result<Self> {
+        let path = Path::new(path);
+
+        // Create parent directory if it doesn't exist
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // If file doesn't exist, create with defaults
+        if !path.exists() {
+            let default_config = Self::default();
+            default_config.save(path.to_str().unwrap())?;
+            return Ok(default_config);
+        }
+
+        let contents = fs::read_to_string(path)?;
+        let config: AppConfig = toml::from_str(&contents)?;
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let toml_string = toml::to_string_pretty(self)?;
+        fs::write(path, toml_string)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use chrono::Local;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, interval};
+
+use crate::app::ServerInfo;
+use crate::irc::{IrcCommand, MessageTags, PresenceKind, UiEvent};
+
+// Stands in for run_irc with --demo: a made-up network that chats on its own
+pub const DEMO_SERVER: &str = "demo";
+const DEMO_NICK: &str = "duck";
+
+// How often one of the scripted lines below arrives
+const DEMO_MESSAGE_INTERVAL: Duration = Duration::from_secs(3);
+
+const DEMO_CHANNELS: &[(&str, &str)] = &[
+    ("#duckirc", "duckIRC demo, nothing here leaves your machine"),
+    ("#rust", "Rust programming language | https://www.rust-lang.org"),
+];
+
+const DEMO_MEMBERS: &[(Option<char>, &str)] = &[
+    (Some('@'), "mallard"),
+    (Some('+'), "ferris"),
+    (None, "teal"),
+    (None, "wigeon"),
+    (None, "gadwall"),
+];
+
+// (channel, nick, text), played in order and then from the start again
+const DEMO_SCRIPT: &[(&str, &str, &str)] = &[
+    ("#duckirc", "mallard", "welcome to the demo, everything you see is scripted"),
+    ("#duckirc", "teal", "try m to browse messages and c for the member list"),
+    ("#rust", "ferris", "anyone else waiting on the new edition?"),
+    ("#duckirc", "wigeon", "duck: Tab completes nicks, give it a go"),
+    ("#rust", "gadwall", "the borrow checker and I have reached an understanding"),
+    ("#duckirc", "teal", "links like https://github.com/DuckTapeMan35/duckIRC open on click"),
+    ("ferris", "ferris", "psst, direct messages show up under the server too"),
+    ("#rust", "mallard", "reminder: be nice, this is a friendly channel"),
+    ("#duckirc", "gadwall", ":help lists every key"),
+];
+
+// The tree needs a server to hang the demo channels from
+pub fn demo_server() -> ServerInfo {
+    ServerInfo {
+        name: DEMO_SERVER.to_string(),
+        is_connected: false,
+        channels: Vec::new(),
+        is_expanded: true,
+        autoconnect: false,
+    }
+}
+
+fn members(nick: &str) -> Vec<(Option<char>, String)> {
+    let mut members: Vec<_> = DEMO_MEMBERS.iter().map(|(p, n)| (*p, n.to_string())).collect();
+    members.push((None, nick.to_string()));
+    members
+}
+
+fn channel_update(channel: &str, clients: Vec<(Option<char>, String)>, is_dm: bool) -> UiEvent {
+    UiEvent::ChannelUpdate {
+        server_name: DEMO_SERVER.to_string(),
+        channel_name: channel.to_string(),
+        topic: None,
+        client_count: clients.len(),
+        clients,
+        is_joined: true,
+        is_dm,
+    }
+}
+
+fn status(text: &str) -> UiEvent {
+    UiEvent::Status { server_name: DEMO_SERVER.to_string(), text: text.to_string() }
+}
+
+pub async fn run_demo(
+    ui_tx: mpsc::UnboundedSender<UiEvent>,
+    mut irc_rx: mpsc::UnboundedReceiver<IrcCommand>,
+) -> Result<()> {
+    let server_name = DEMO_SERVER.to_string();
+    let mut nick = DEMO_NICK.to_string();
+    ui_tx.send(UiEvent::Connecting { server_name: server_name.clone() }).ok();
+    ui_tx.send(UiEvent::Registering { server_name: server_name.clone() }).ok();
+    ui_tx.send(UiEvent::Connected { nick: nick.clone(), server_name: server_name.clone() }).ok();
+    ui_tx.send(status("Demo mode: no network connection is made")).ok();
+
+    // Every channel joined so far with its members, DMs have the other nick alone
+    let mut channels: HashMap<String, Vec<(Option<char>, String)>> = HashMap::new();
+    for (channel, topic) in DEMO_CHANNELS {
+        channels.insert(channel.to_string(), members(&nick));
+        ui_tx.send(channel_update(channel, members(&nick), false)).ok();
+        ui_tx.send(UiEvent::Topic {
+            server_name: server_name.clone(),
+            channel: channel.to_string(),
+            topic: Some(topic.to_string()),
+            set_by: Some("mallard".to_string()),
+            set_at: Some(Local::now()),
+        }).ok();
+        ui_tx.send(UiEvent::Joined {
+            server_name: server_name.clone(),
+            channel: channel.to_string(),
+            count: DEMO_MEMBERS.len() + 1,
+        }).ok();
+    }
+
+    let mut ticker = interval(DEMO_MESSAGE_INTERVAL);
+    let mut script = DEMO_SCRIPT.iter().cycle();
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let Some((channel, sender, text)) = script.next() else {
+                    continue;
+                };
+                if !channels.contains_key(*channel) {
+                    let is_dm = !channel.starts_with('#');
+                    let clients = if is_dm { vec![(None, channel.to_string())] } else { members(&nick) };
+                    channels.insert(channel.to_string(), clients.clone());
+                    ui_tx.send(channel_update(channel, clients, is_dm)).ok();
+                }
+                ui_tx.send(UiEvent::PrivMsg {
+                    server_name: server_name.clone(),
+                    channel: channel.to_string(),
+                    nick: sender.to_string(),
+                    text: text.to_string(),
+                    time: Local::now(),
+                    tags: MessageTags::default(),
+                }).ok();
+            }
+            cmd = irc_rx.recv() => {
+                let Some(cmd) = cmd else {
+                    return Ok(());
+                };
+                match cmd {
+                    // The UI echoes our own messages, nobody on the demo network answers
+                    IrcCommand::PrivMsg(_) | IrcCommand::Reply { .. } | IrcCommand::Away(_) => {}
+                    IrcCommand::SetCurrentChannel(channel) => {
+                        if let Some(clients) = channels.get(&channel) {
+                            let is_dm = !channel.starts_with('#');
+                            ui_tx.send(channel_update(&channel, clients.clone(), is_dm)).ok();
+                        }
+                    }
+                    IrcCommand::Join { channel, .. } => {
+                        let clients = vec![(Some('@'), nick.clone())];
+                        channels.insert(channel.clone(), clients.clone());
+                        ui_tx.send(channel_update(&channel, clients, false)).ok();
+                        ui_tx.send(UiEvent::Presence {
+                            server_name: server_name.clone(),
+                            channel: Some(channel.clone()),
+                            kind: PresenceKind::Join,
+                            text: format!("{} joined {}", nick, channel),
+                            time: Local::now(),
+                        }).ok();
+                    }
+                    IrcCommand::Part(channel) => {
+                        channels.remove(&channel);
+                        ui_tx.send(UiEvent::ChannelUpdate {
+                            server_name: server_name.clone(),
+                            channel_name: channel,
+                            topic: None,
+                            client_count: 0,
+                            clients: Vec::new(),
+                            is_joined: false,
+                            is_dm: false,
+                        }).ok();
+                    }
+                    IrcCommand::Nick(new) => {
+                        for clients in channels.values_mut() {
+                            for member in clients.iter_mut().filter(|m| m.1 == nick) {
+                                member.1 = new.clone();
+                            }
+                        }
+                        ui_tx.send(UiEvent::NickChange {
+                            server_name: server_name.clone(),
+                            old: std::mem::replace(&mut nick, new.clone()),
+                            new,
+                            channels: channels.keys().cloned().collect(),
+                            is_self: true,
+                        }).ok();
+                    }
+                    IrcCommand::Disconnect => {
+                        ui_tx.send(UiEvent::Disconnected { server_name: server_name.clone() }).ok();
+                        return Ok(());
+                    }
+                    _ => {
+                        ui_tx.send(status("Not available in demo mode")).ok();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// Static table behind the help overlay, keep it in sync when adding keys or commands
+pub const HELP_SECTIONS: &[(&str, &[(&str, &str)])] = &[
+    ("Normal mode", &[
+        ("i", "Enter Insert mode"),
+        ("a / A", "Append after cursor / at end of line"),
+        ("v", "Enter Visual mode"),
+        (":", "Enter Command mode"),
+        ("s", "Enter Server mode"),
+        ("m", "Enter Messages mode"),
+        ("c", "Enter Clients mode"),
+        ("Tab", "Cycle Server -> Messages -> Clients"),
+        ("Alt-Left / Alt-Right", "Previous / next joined channel (any mode)"),
+        ("Alt-a", "Jump to the next channel with unread messages (any mode)"),
+        ("F3 / F4", "Keep the server tree / clients panel open in every mode"),
+        ("h / l", "Move cursor left / right"),
+        ("w / b / e", "Next word / previous word / end of word"),
+        ("W / B / E", "Same as w / b / e for WORDs"),
+        ("gg / G", "Jump to start / end of input"),
+        ("0 / $", "First / last character of the input"),
+        ("<n>|", "Jump to column n"),
+        ("<n>h, <n>w, ...", "Repeat a motion n times"),
+        ("dd", "Delete the whole input"),
+        ("r<char>", "Replace the character under the cursor"),
+        ("R", "Enter Replace mode, typing overwrites"),
+        (".", "Repeat the last dd, diw, p or r<char>"),
+        ("o / O", "Send the input, then Insert / stay in Normal mode"),
+        ("diw", "Delete inner word"),
+        ("p", "Paste the yank register"),
+        ("\"+p", "Paste the system clipboard"),
+        ("C", "Clear the view, like :clear"),
+        ("?", "Show this help"),
+        ("q", "Quit"),
+    ]),
+    ("Insert mode", &[
+        ("Enter", "Send message"),
+        ("Esc", "Return to Normal mode"),
+        ("Tab", "Complete a nick or select in the popup listing several, or switch to Server mode"),
+        ("Up / Down, Enter", "Pick a nick in the completion popup, Esc closes it"),
+        ("Left / Right", "Move cursor"),
+        ("Backspace", "Delete previous character"),
+        ("Alt-Enter / Shift-Enter", "New line, Enter sends each line as a message"),
+        ("Ctrl-v", "Paste the system clipboard"),
+        ("Ctrl-w", "Delete the previous word"),
+        ("Ctrl-u / Ctrl-k", "Delete to start / end of input, yanking it"),
+    ]),
+    ("Visual mode", &[
+        ("h / l, w / b / e", "Extend the selection"),
+        ("y", "Yank the selection"),
+        ("d / x", "Delete the selection"),
+        ("Esc", "Return to Normal mode"),
+    ]),
+    ("Server mode", &[
+        ("Up / Down", "Move through the server tree"),
+        ("Enter", "Connect/disconnect server or join channel"),
+        ("/", "Filter the tree by name, Esc clears the filter"),
+        ("< / >", "Narrow / widen the tree (tree_width)"),
+        ("m / c / i", "Go to Messages / Clients / Insert mode"),
+        (":", "Enter Command mode"),
+        ("Esc / q", "Return to Normal mode"),
+    ]),
+    ("Messages mode", &[
+        ("j / k", "Move through messages"),
+        ("gg / G", "Jump to first / last message"),
+        ("<n>G", "Jump to message number n"),
+        ("<n>j / <n>k", "Move n messages down / up"),
+        ("PageUp / PageDown", "Scroll a page (page_scroll_lines), also in Normal mode"),
+        ("Ctrl-d / Ctrl-u", "Scroll half a screen down / up"),
+        ("h / l", "Scroll sideways when wrap is off"),
+        ("V", "Start/stop selecting whole messages"),
+        ("y", "Yank the selected message, or all V-selected ones"),
+        ("Y", "Same as y as \"[HH:MM] <nick> text\" lines"),
+        ("s / c", "Go to Server / Clients mode"),
+        (":", "Enter Command mode"),
+        ("Esc / q", "Return to Normal mode"),
+    ]),
+    ("Clients mode", &[
+        ("j / k", "Move through clients"),
+        ("gg / G", "Jump to first / last client"),
+        ("Enter", "Start a direct message"),
+        ("/", "Filter nicks (letters in order), Esc clears the filter"),
+        ("< / >", "Narrow / widen the panel (clients_width)"),
+        ("y", "Yank the selected nick and return to Normal mode"),
+        ("Y / right-click", "Copy the selected nick, staying in Clients mode"),
+        ("@", "Insert \"nick: \" into the input and enter Insert mode"),
+        ("s / m / i", "Go to Server / Messages / Insert mode"),
+        ("Esc / q", "Return to Normal mode"),
+    ]),
+    ("Vimless mode", &[
+        ("Enter", "Send message or run a /command"),
+        ("Ctrl-v", "Paste the system clipboard"),
+        ("Tab", "Complete a nick, or select in the popup listing several (Up / Down, Enter)"),
+        ("Up / Down", "Recall previously sent messages and /commands"),
+        ("Home / End", "Move to the start / end of the input"),
+        ("Alt-Enter / Shift-Enter", "New line in the message"),
+        ("Ctrl-w", "Delete the previous word"),
+        ("Ctrl-u / Ctrl-k", "Delete to start / end of input, yanking it"),
+        ("/quit, /q", "Quit"),
+        ("/vim, /v", "Return to Normal mode"),
+        ("/connect, /disconnect", "Same as the Command mode commands"),
+        ("/join, /msg, /nick, /list", "Same as the Command mode commands"),
+    ]),
+    ("Commands", &[
+        ("connect <server|host:port>", "Connect to a server"),
+        ("disconnect", "Disconnect from the server"),
+        ("reconnect", "Reconnect and rejoin the last server's channels"),
+        ("join <#channel> [key]", "Join a channel, with a key if it has one"),
+        ("cycle [#channel]", "Part and rejoin the current or named channel"),
+        ("reply <message>", "Answer the message selected in Messages mode"),
+        ("topic [text]", "Show the topic and who set it, or set a new one"),
+        ("list", "Browse the server's channels (type to filter, Enter joins)"),
+        ("msg <user> <message>", "Send a direct message"),
+        ("nick <nickname>", "Change your nickname"),
+        ("servers", "List saved servers"),
+        ("add_server <name> <addr> <port> [tls]", "Save a server"),
+        ("remove_server <name>", "Remove a saved server"),
+        ("edit_server <name> <field> <value>", "Change a saved server's name, address, port, tls, password or autoconnect"),
+        ("status", "Show connection status and the server's limits"),
+        ("whoami", "Show your nick, username, server and services status"),
+        ("raw <line>", "Send a line to the server verbatim"),
+        ("caps", "Show the IRCv3 capabilities the server enabled"),
+        ("set [<key> <value>]", "Change a setting: wrap on|trim|off, logging on|off, nick_column <n>, mouse on|off, tab_bar on|off, tree_width <n>, clients_width <n>"),
+        ("reload_config, reload", "Re-read servers.toml, config.toml and theme.toml"),
+        ("ignore [nick|mask]", "Hide a nick's messages, or list ignored nicks"),
+        ("unignore <nick|mask>", "Stop ignoring a nick"),
+        ("notify [nick]", "Get told when a nick comes online or leaves, or list watched nicks"),
+        ("unnotify <nick>", "Stop watching a nick"),
+        ("clear, c", "Clear the view (scroll up to see old messages)"),
+        ("clear-buffer", "Delete the current channel's scrollback"),
+        ("save-buffer <path>", "Write the current channel's messages to a file"),
+        ("mark-read", "Remove the \"new messages\" line from the current channel"),
+        ("clear-all", "Delete every channel's scrollback"),
+        ("vimless", "Switch to Vimless mode"),
+        ("help", "Show this help"),
+        ("quit, q", "Quit"),
+    ]),
+];
@@ -1,26 +1,230 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local};
 use futures_util::StreamExt;
 use irc::client::prelude::*;
 use irc::proto::Command;
+use irc::proto::message::Tag;
 use tokio::sync::mpsc;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use dirs::home_dir;
 
 use crate::servers::ServerConfig;
+use crate::config::AppConfig;
+use irc::proto::CapSubCommand;
+
+// Nick used when runtime_config.toml can't be read
+pub const DEFAULT_NICK: &str = "guest";
+
+// IRC line limit including CRLF, and the longest hostname the server may put in our prefix
+const MAX_LINE_LEN: usize = 512;
+const MAX_HOST_LEN: usize = 63;
+
+// How many RPL_LIST entries to collect before handing them to the UI
+const LIST_BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct ChannelListEntry {
+    pub name: String,
+    pub users: usize,
+    pub topic: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceKind {
+    Join,
+    Part,
+    Quit,
+}
+
+// Where a connection is between Connect and RPL_WELCOME
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,  // resolving, TCP and TLS handshake
+    Registering, // NICK/USER sent, waiting for RPL_WELCOME
+    Connected,
+}
+
+impl ConnectionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionState::Disconnected => "Disconnected",
+            ConnectionState::Connecting => "Connecting",
+            ConnectionState::Registering => "Registering",
+            ConnectionState::Connected => "Connected",
+        }
+    }
+}
+
+// What the server told us about itself in RPL_ISUPPORT (005)
+#[derive(Debug, Clone)]
+pub struct ISupport {
+    pub chantypes: String,
+    // (mode, symbol) membership prefixes from highest to lowest rank, e.g. ('o', '@')
+    pub prefix: Vec<(char, char)>,
+    pub nicklen: Option<usize>,
+    pub channellen: Option<usize>,
+    pub topiclen: Option<usize>,
+    // how many mode changes one MODE line may carry, None when unlimited
+    pub modes: Option<usize>,
+    // the server takes MONITOR, which the notify list relies on
+    pub monitor: bool,
+}
+
+impl Default for ISupport {
+    fn default() -> Self {
+        Self {
+            // every prefix RFC 2811 knows, until the server says otherwise
+            chantypes: "#&+!".to_string(),
+            prefix: vec![('q', '~'), ('a', '&'), ('o', '@'), ('h', '%'), ('v', '+')],
+            nicklen: None,
+            channellen: None,
+            topiclen: None,
+            modes: Some(3),
+            monitor: false,
+        }
+    }
+}
+
+impl ISupport {
+    // Fold one 005 line's KEY=VALUE tokens in, unknown keys are ignored
+    fn apply(&mut self, tokens: &[String]) {
+        let defaults = ISupport::default();
+        for token in tokens {
+            let (key, value) = token.split_once('=').unwrap_or((token, ""));
+            match key {
+                "CHANTYPES" => self.chantypes = value.to_string(),
+                "PREFIX" => self.prefix = parse_prefix(value),
+                "NICKLEN" => self.nicklen = value.parse().ok(),
+                "CHANNELLEN" => self.channellen = value.parse().ok(),
+                "TOPICLEN" => self.topiclen = value.parse().ok(),
+                "MODES" => self.modes = value.parse().ok(),
+                "MONITOR" => self.monitor = true,
+                "-CHANTYPES" => self.chantypes = defaults.chantypes.clone(),
+                "-PREFIX" => self.prefix = defaults.prefix.clone(),
+                "-NICKLEN" => self.nicklen = None,
+                "-CHANNELLEN" => self.channellen = None,
+                "-TOPICLEN" => self.topiclen = None,
+                "-MODES" => self.modes = defaults.modes,
+                "-MONITOR" => self.monitor = false,
+                _ => {}
+            }
+        }
+    }
+
+    // '#', '&' or whatever else CHANTYPES lists, anything else is a nick
+    pub fn is_channel(&self, name: &str) -> bool {
+        name.starts_with(|c| self.chantypes.contains(c))
+    }
+
+    // The symbols alone ("~&@%+"), highest rank first
+    pub fn prefix_symbols(&self) -> Vec<char> {
+        self.prefix.iter().map(|(_, symbol)| *symbol).collect()
+    }
+
+    // The channel mode behind a membership symbol, '@' -> 'o'
+    pub fn prefix_mode(&self, symbol: char) -> Option<char> {
+        self.prefix.iter().find(|(_, s)| *s == symbol).map(|(mode, _)| *mode)
+    }
+
+    // The other way around, 'o' -> '@', None for modes that aren't memberships
+    pub fn prefix_symbol(&self, mode: char) -> Option<char> {
+        self.prefix.iter().find(|(m, _)| *m == mode).map(|(_, symbol)| *symbol)
+    }
+}
+
+// "(qaohv)~&@%+" into pairs, an empty value means the server has no prefixes
+fn parse_prefix(value: &str) -> Vec<(char, char)> {
+    let Some((modes, symbols)) = value.strip_prefix('(').and_then(|v| v.split_once(')')) else {
+        return Vec::new();
+    };
+    modes.chars().zip(symbols.chars()).collect()
+}
+
+// Checked before JOIN so typos get a useful message instead of a server numeric
+pub fn is_valid_channel_name(name: &str, isupport: &ISupport) -> Result<()> {
+    let Some(first) = name.chars().next() else {
+        bail!("Channel name is empty");
+    };
+    if !isupport.chantypes.contains(first) {
+        let prefixes: Vec<String> = isupport.chantypes.chars().map(|c| c.to_string()).collect();
+        bail!("Channel must start with {}", prefixes.join(" or "));
+    }
+    if name.chars().count() < 2 {
+        bail!("Channel name needs something after {}", first);
+    }
+    if let Some(bad) = name.chars().find(|c| *c == ' ' || *c == ',' || c.is_control()) {
+        bail!("Channel name can't contain {:?}", bad);
+    }
+    if let Some(max) = isupport.channellen && name.len() > max {
+        bail!("Channel name is longer than the server allows ({} characters)", max);
+    }
+    Ok(())
+}
+
+// Same idea for NICK, the server would otherwise truncate or reject it
+pub fn is_valid_nick(nick: &str, isupport: &ISupport) -> Result<()> {
+    if nick.is_empty() {
+        bail!("Nick is empty");
+    }
+    if let Some(bad) = nick.chars().find(|c| *c == ' ' || *c == ',' || c.is_control()) {
+        bail!("Nick can't contain {:?}", bad);
+    }
+    if nick.starts_with(|c: char| c.is_ascii_digit() || c == '-' || isupport.chantypes.contains(c)) {
+        bail!("Nick can't start with {}", nick.chars().next().unwrap_or_default());
+    }
+    if let Some(max) = isupport.nicklen && nick.len() > max {
+        bail!("Nick is longer than the server allows ({} characters)", max);
+    }
+    Ok(())
+}
+
+// The IRCv3 tags a chat message keeps
+#[derive(Debug, Clone, Default)]
+pub struct MessageTags {
+    pub msgid: Option<String>,
+    pub reply_to: Option<String>,
+}
 
 #[derive(Debug)]
 pub enum UiEvent {
-    Connected { nick: String, server_name: String },
+    Connecting { server_name: String },
+    Registering { server_name: String },
+    Connected { nick: String, server_name: String }, // sent once the server welcomed us
     Disconnected {server_name: String},
     Message(String),
+    Status { server_name: String, text: String }, // goes to the server's status buffer
+    PrivMsg { server_name: String, channel: String, nick: String, text: String, time: DateTime<Local>, tags: MessageTags },
+    // REDACT of an earlier message, by its msgid
+    Redact { server_name: String, channel: String, msgid: String },
+    // a nick on the notify list came online or went offline
+    Notify { server_name: String, nick: String, online: bool },
+    // "X sets +o Y" and other channel mode changes
+    ChannelMode { server_name: String, channel: String, text: String },
+    // join/part/quit lines, channel is None when it isn't tied to one (QUIT)
+    Presence { server_name: String, channel: Option<String>, kind: PresenceKind, text: String, time: DateTime<Local> },
     Error(String),
+    ChannelList { entries: Vec<ChannelListEntry>, done: bool },
+    Identified { server_name: String, identified: bool },
+    CapsChanged { caps: Vec<String> }, // the full enabled set after ACK/DEL
+    ServerRenamed { old: String, new: String }, // edit_server changed a saved server's name
+    ISupport(ISupport), // everything learned from 005 so far
+    // RPL_TOPIC, RPL_TOPICWHOTIME or a TOPIC change, None is what the message didn't carry
+    Topic { server_name: String, channel: String, topic: Option<String>, set_by: Option<String>, set_at: Option<DateTime<Local>> },
+    // the first NAMES after our own JOIN is in, count is the member total
+    Joined { server_name: String, channel: String, count: usize },
+    // someone (maybe us) changed nick, channels are the ones they share with us
+    NickChange { server_name: String, old: String, new: String, channels: Vec<String>, is_self: bool },
     ChannelUpdate {
         server_name: String,
         channel_name: String,
         topic: Option<String>,
         client_count: usize,
-        clients: Vec<String>,
+        clients: Vec<(Option<char>, String)>, // (highest membership prefix, nick)
         is_joined: bool,
         is_dm: bool,
     },
@@ -29,14 +233,54 @@ pub enum UiEvent {
 #[derive(Debug)]
 pub enum IrcCommand {
     Connect(String),      // Connect to server (name or address:port)
-    Join(String),         // Join a channel
+    Join { channel: String, key: Option<String> }, // Join a channel, key for +k channels
+    Part(String),         // Leave a channel
     PrivMsg(String),      // Send a message
     Nick(String),         // Change nickname
     ListServers,          // List saved servers
     AddServer { name: String, address: String, port: u16, use_tls: bool },
     RemoveServer(String), // Remove server by name
+    EditServer { name: String, field: String, value: String },
     Disconnect,          // Disconnect from server
     SetCurrentChannel(String), // Update the channel we are viewing
+    RejoinAll(Vec<(String, Option<String>)>), // Rejoin these channels, with their keys, once registered
+    ListChannels,         // Request the server's channel list
+    ReloadConfig,         // Re-read servers.toml from disk
+    WhoAmI,               // Report who we are on the current connection
+    Raw(String),          // Send a line to the server verbatim
+    ListCaps,             // Report the IRCv3 caps the server granted
+    Away(Option<String>), // Mark ourselves away with a message, None marks us back
+    Topic { channel: String, topic: String }, // Set a channel's topic
+    Reply { channel: String, msgid: String, text: String }, // PRIVMSG to channel tagged +draft/reply
+    Monitor { nick: String, add: bool }, // Start/stop watching a nick for the notify list
+}
+
+// Join/PrivMsg/Nick issued while registering, replayed in order after RPL_WELCOME
+#[derive(Debug, Default)]
+struct CommandQueue {
+    // Set by RPL_WELCOME, the server ignores JOINs before that
+    registered: bool,
+    pending: Vec<IrcCommand>,
+}
+
+impl CommandQueue {
+    // Hold a command until registration, true for the first one so the user hears about it once
+    fn push(&mut self, cmd: IrcCommand) -> bool {
+        self.pending.push(cmd);
+        self.pending.len() == 1
+    }
+
+    // RPL_WELCOME arrived, hand back what was held in the order it came
+    fn welcome(&mut self) -> Vec<IrcCommand> {
+        self.registered = true;
+        std::mem::take(&mut self.pending)
+    }
+
+    // Connecting, disconnecting or losing the connection starts over unregistered
+    fn reset(&mut self) {
+        self.registered = false;
+        self.pending.clear();
+    }
 }
 
 pub async fn run_irc(
@@ -48,6 +292,28 @@ pub async fn run_irc(
     let mut current_channel = String::new();
     let mut current_server_name = String::new();
     let mut accumulated_channels: Vec<String> = Vec::new();
+    // RPL_NAMREPLY lines collected per channel until RPL_ENDOFNAMES
+    let mut pending_names: HashMap<String, Vec<(Option<char>, String)>> = HashMap::new();
+    // Last complete member list per channel, kept current across NICK changes
+    let mut channel_members: HashMap<String, Vec<(Option<char>, String)>> = HashMap::new();
+    // Channels we just joined, their first RPL_ENDOFNAMES gets a summary line
+    let mut joining: HashSet<String> = HashSet::new();
+    // The crate's current_nickname() doesn't follow NICK changes, so track it ourselves
+    let mut current_nick = String::new();
+    let mut rejoin_channels: Vec<(String, Option<String>)> = Vec::new();
+    // config.toml's notify list, sent with MONITOR once the server says it has it
+    let mut notify_nicks: Vec<String> = Vec::new();
+    let mut list_batch: Vec<ChannelListEntry> = Vec::new();
+    let mut identified = false;
+    let mut queue = CommandQueue::default();
+    let mut current_username = String::new();
+    let mut wanted_caps: Vec<String> = Vec::new();
+    let mut offered_caps: Vec<String> = Vec::new();
+    let mut enabled_caps: Vec<String> = Vec::new();
+    let mut cap_negotiating = false;
+    let mut isupport = ISupport::default();
+    // Reason from the server's ERROR line, which comes right before it hangs up
+    let mut server_error: Option<String> = None;
     let config_dir = ensure_config_dir()?;
     let server_config_path = config_dir.join("servers.toml");
     if !server_config_path.exists() {
@@ -56,7 +322,30 @@ pub async fn run_irc(
     let mut server_config = ServerConfig::load(server_config_path.to_str().expect("Invalid path"))
         .unwrap_or_else(|_| ServerConfig::default_config());
 
-    loop {
+    'events: loop {
+        // Drop a dead connection and wait for the next Connect, it shouldn't take the whole task down
+        macro_rules! lose_connection {
+            ($reason:expr) => {{
+                client = None;
+                stream = None;
+                queue.reset();
+                ui_tx.send(UiEvent::Error(format!(
+                    "Lost connection to {}: {}, use :reconnect to try again",
+                    current_server_name, $reason
+                ))).ok();
+                ui_tx.send(UiEvent::Disconnected { server_name: current_server_name.clone() }).ok();
+                continue 'events;
+            }};
+        }
+        // A send only fails once the connection is gone
+        macro_rules! try_send {
+            ($send:expr) => {
+                if let Err(e) = $send {
+                    lose_connection!(e)
+                }
+            };
+        }
+
         tokio::select! {
             Some(cmd) = irc_rx.recv() => {
                 match cmd {
@@ -67,73 +356,119 @@ pub async fn run_irc(
                         }
                         
                         // Try to find server by name first
-                        let (host, port, use_tls, server_name) = if let Some(server) = server_config.get_server(&server_str) {
-                            (server.address.clone(), server.port, server.use_tls, server.name.clone())
+                        let (host, port, use_tls, server_name, password) = if let Some(server) = server_config.get_server(&server_str) {
+                            (server.address.clone(), server.port, server.use_tls, server.name.clone(), server.password.clone())
                         } else {
                             // Parse as address:port
                             let (h, p, t) = parse_server_address(&server_str);
-                            (h, p, t, server_str.clone())
+                            (h, p, t, server_str.clone(), None)
                         };
 
                         current_server_name = server_name.clone();
                         accumulated_channels.clear();
+                        pending_names.clear();
+                        channel_members.clear();
+                        identified = false;
+                        rejoin_channels.clear();
+                        queue.reset();
+                        isupport = ISupport::default();
+                        ui_tx.send(UiEvent::Connecting { server_name: server_name.clone() }).ok();
 
+                        // A broken runtime config shouldn't make connecting impossible
+                        let nickname = get_user_nick().unwrap_or_else(|e| {
+                            ui_tx.send(UiEvent::Error(format!(
+                                "Failed to read nick from runtime_config.toml ({}), using {}",
+                                e, DEFAULT_NICK
+                            ))).ok();
+                            DEFAULT_NICK.to_string()
+                        });
+
+                        current_nick = nickname.clone();
                         let config = Config {
-                            nickname: Some(get_user_nick()?),
+                            nickname: Some(nickname),
                             server: Some(host.clone()),
                             port: Some(port),
                             use_tls: Some(use_tls),
+                            password: password.clone(),
                             ..Default::default()
                         };
 
-                        match Client::from_config(config).await {
+                        current_username = config.username().to_string();
+                        let realname = config.real_name().to_string();
+
+                        let app_config = AppConfig::load(get_config_dir().join("config.toml").to_str().expect("invalid path"))
+                            .unwrap_or_default();
+                        wanted_caps = app_config.caps;
+                        notify_nicks = app_config.notify;
+                        offered_caps.clear();
+                        enabled_caps.clear();
+                        cap_negotiating = true;
+                        server_error = None;
+                        joining.clear();
+
+                        // An unreachable host can take minutes to fail on its own
+                        let connect_timeout = Duration::from_secs(app_config.connect_timeout_secs);
+                        let connected = match tokio::time::timeout(connect_timeout, Client::from_config(config)).await {
+                            Ok(result) => result,
+                            Err(_) => {
+                                ui_tx.send(UiEvent::Error(format!(
+                                    "Timed out connecting to {}:{} after {}s",
+                                    host, port, app_config.connect_timeout_secs
+                                ))).ok();
+                                ui_tx.send(UiEvent::Disconnected { server_name: server_name.clone() }).ok();
+                                continue;
+                            }
+                        };
+                        match connected {
                             Ok(mut c) => {
-                                if let Err(e) = c.identify() {
+                                // Like Client::identify, but opens CAP negotiation instead of ending it
+                                // right away. Registration waits for our CAP END.
+                                // PASS has to come before NICK/USER
+                                let registered = c.send_cap_ls(NegotiationVersion::V302)
+                                    .and_then(|_| match &password {
+                                        Some(password) => c.send(Command::PASS(password.clone())),
+                                        None => Ok(()),
+                                    })
+                                    .and_then(|_| c.send(Command::NICK(c.current_nickname().to_string())))
+                                    .and_then(|_| c.send(Command::USER(current_username.clone(), "0".to_string(), realname)));
+                                if let Err(e) = registered {
                                     ui_tx.send(UiEvent::Error(format!("Failed to identify: {}", e))).ok();
+                                    ui_tx.send(UiEvent::Disconnected { server_name: server_name.clone() }).ok();
                                     continue;
                                 }
 
-                                let nick = c.current_nickname().to_string();
-                                ui_tx.send(UiEvent::Connected { 
-                                    nick: nick.clone(),
-                                    server_name: server_name.clone(),
-                                }).ok();
-
-                                stream = Some(c.stream()?);
-                                client = Some(c);
+                                match c.stream() {
+                                    Ok(s) => {
+                                        ui_tx.send(UiEvent::Registering { server_name: server_name.clone() }).ok();
+                                        stream = Some(s);
+                                        client = Some(c);
+                                    }
+                                    Err(e) => {
+                                        ui_tx.send(UiEvent::Error(format!("Failed to connect: {}", e))).ok();
+                                        ui_tx.send(UiEvent::Disconnected { server_name: server_name.clone() }).ok();
+                                    }
+                                }
                             }
                             Err(e) => {
                                 ui_tx.send(UiEvent::Error(format!("Failed to connect: {}", e))).ok();
+                                ui_tx.send(UiEvent::Disconnected { server_name: server_name.clone() }).ok();
                             }
                         }
                     }
 
-                    IrcCommand::Join(channel) => {
-                        if let Some(c) = &client {
-                            c.send_join(&channel)?;
-                            current_channel = channel;
-                            c.send(Command::NAMES(Some(current_channel.clone()), None))?;
-                        } else {
-                            ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
-                        }
-                    }
-
-                    IrcCommand::PrivMsg(msg) => {
-                        if let Some(c) = &client {
-                            if current_channel.is_empty() {
-                                ui_tx.send(UiEvent::Error("No channel joined".to_string())).ok();
-                            } else {
-                                c.send_privmsg(&current_channel, &msg)?;
-                            }
-                        } else {
-                            ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
+                    // The server ignores these before RPL_WELCOME, hold them until then
+                    IrcCommand::Join { .. } | IrcCommand::PrivMsg(_) | IrcCommand::Nick(_) if client.is_some() && !queue.registered => {
+                        if queue.push(cmd) {
+                            ui_tx.send(UiEvent::Status {
+                                server_name: current_server_name.clone(),
+                                text: "Not registered yet, queued until the server welcomes us".to_string(),
+                            }).ok();
                         }
                     }
 
-                    IrcCommand::Nick(nick) => {
+                    IrcCommand::Join { .. } | IrcCommand::PrivMsg(_) | IrcCommand::Nick(_) => {
                         if let Some(c) = &client {
-                            c.send(Command::NICK(nick.clone()))?;
-                            set_user_nick(&nick).ok();
+                            try_send!(send_user_command(c, cmd, &mut current_channel, &current_nick, &current_username, &ui_tx));
                         } else {
                             ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
                         }
@@ -174,10 +509,35 @@ pub async fn run_irc(
                         }
                     }
                     
+                    IrcCommand::EditServer { name, field, value } => {
+                        match server_config.update_server(&name, &field, &value) {
+                            Ok(()) => {
+                                if let Err(e) = server_config.save(server_config_path.to_str().expect("invalid path")) {
+                                    ui_tx.send(UiEvent::Error(format!("Failed to save config: {}", e))).ok();
+                                } else {
+                                    ui_tx.send(UiEvent::Message(format!("Updated server {}: {} = {}", name, field, value))).ok();
+                                    if field == "name" && value != name {
+                                        // Events for the open connection carry the new name from here on
+                                        if current_server_name == name {
+                                            current_server_name = value.clone();
+                                        }
+                                        ui_tx.send(UiEvent::ServerRenamed { old: name, new: value }).ok();
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                ui_tx.send(UiEvent::Error(e.to_string())).ok();
+                            }
+                        }
+                    }
+
                     IrcCommand::Disconnect => {
                         if let Some(client) = client.take() {
                             drop(client);
                         }
+                        // Otherwise its end would be reported as a lost connection
+                        stream = None;
+                        queue.reset();
 
                         ui_tx
                             .send(UiEvent::Disconnected {
@@ -186,61 +546,432 @@ pub async fn run_irc(
                             .ok();
                     }
                     IrcCommand::SetCurrentChannel(channel) => {
+                        // Hand back the members we already know so the clients panel follows the switch
+                        if let Some(members) = channel_members.get(&channel) {
+                            ui_tx.send(UiEvent::ChannelUpdate {
+                                server_name: current_server_name.clone(),
+                                channel_name: channel.clone(),
+                                topic: None,
+                                client_count: members.len(),
+                                clients: members.clone(),
+                                is_joined: true,
+                                is_dm: false,
+                            }).ok();
+                        }
                         current_channel = channel;
                     }
+                    IrcCommand::RejoinAll(channels) => {
+                        // JOINs sent before registration are rejected, wait for RPL_WELCOME
+                        if client.is_some() {
+                            rejoin_channels.extend(channels);
+                        }
+                    }
+                    IrcCommand::ReloadConfig => {
+                        match ServerConfig::load(server_config_path.to_str().expect("invalid path")) {
+                            Ok(config) => {
+                                server_config = config;
+                                ui_tx.send(UiEvent::Message(format!(
+                                    "Reloaded servers.toml ({} servers)",
+                                    server_config.servers.len()
+                                ))).ok();
+                            }
+                            Err(e) => {
+                                ui_tx.send(UiEvent::Error(format!("Failed to reload servers.toml: {}", e))).ok();
+                            }
+                        }
+                    }
+                    IrcCommand::WhoAmI => {
+                        if client.is_some() {
+                            ui_tx.send(UiEvent::Message(format!("Nick: {}", current_nick))).ok();
+                            ui_tx.send(UiEvent::Message(format!("Username: {}", current_username))).ok();
+                            ui_tx.send(UiEvent::Message(format!("Server: {}", current_server_name))).ok();
+                            ui_tx.send(UiEvent::Message(format!(
+                                "Identified with services: {}",
+                                if identified { "yes" } else { "no" }
+                            ))).ok();
+                        } else {
+                            ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
+                        }
+                    }
+                    IrcCommand::Raw(line) => {
+                        if let Some(c) = &client {
+                            match line.parse::<Message>() {
+                                Ok(message) => {
+                                    try_send!(c.send(message));
+                                    ui_tx.send(UiEvent::Status {
+                                        server_name: current_server_name.clone(),
+                                        text: format!("→ {}", line),
+                                    }).ok();
+                                }
+                                Err(e) => {
+                                    ui_tx.send(UiEvent::Error(format!("Invalid raw line '{}': {}", line, e))).ok();
+                                }
+                            }
+                        } else {
+                            ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
+                        }
+                    }
+                    IrcCommand::ListCaps => {
+                        let text = if client.is_none() {
+                            "Not connected".to_string()
+                        } else if enabled_caps.is_empty() {
+                            "No capabilities enabled".to_string()
+                        } else {
+                            format!("Enabled capabilities: {}", enabled_caps.join(", "))
+                        };
+                        ui_tx.send(UiEvent::Status {
+                            server_name: current_server_name.clone(),
+                            text,
+                        }).ok();
+                    }
+                    IrcCommand::Part(channel) => {
+                        if let Some(c) = &client {
+                            try_send!(c.send_part(&channel));
+                        } else {
+                            ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
+                        }
+                    }
+                    IrcCommand::Away(message) => {
+                        if let Some(c) = &client {
+                            try_send!(c.send(Command::AWAY(message)));
+                        }
+                    }
+                    IrcCommand::Monitor { nick, add } => {
+                        if add {
+                            notify_nicks.push(nick.clone());
+                        } else {
+                            notify_nicks.retain(|n| !n.eq_ignore_ascii_case(&nick));
+                        }
+                        if queue.registered && isupport.monitor && let Some(c) = &client {
+                            let sign = if add { "+" } else { "-" };
+                            try_send!(c.send(Command::Raw("MONITOR".to_string(), vec![sign.to_string(), nick])));
+                        }
+                    }
+                    IrcCommand::Topic { channel, topic } => {
+                        if let Some(c) = &client {
+                            try_send!(c.send(Command::TOPIC(channel, Some(topic))));
+                        }
+                    }
+                    IrcCommand::Reply { channel, msgid, text } => {
+                        if let Some(c) = &client {
+                            // Every chunk answers the same message, tags don't count towards the 512 bytes
+                            for chunk in split_message(&text, privmsg_room(&current_nick, &current_username, &channel)) {
+                                try_send!(c.send(Message {
+                                    tags: Some(vec![Tag("+draft/reply".to_string(), Some(msgid.clone()))]),
+                                    prefix: None,
+                                    command: Command::PRIVMSG(channel.clone(), chunk.to_string()),
+                                }));
+                            }
+                        }
+                    }
+                    IrcCommand::ListChannels => {
+                        if let Some(c) = &client {
+                            list_batch.clear();
+                            try_send!(c.send(Command::LIST(None, None)));
+                        } else {
+                            ui_tx.send(UiEvent::Error("Not connected yet".to_string())).ok();
+                        }
+                    }
                 }
             }
 
             // Handle incoming IRC messages
-            Some(irc_msg) = async {
+            irc_msg = async {
                 if let Some(s) = &mut stream { s.next().await } else { None }
-            } => {
-                let msg = irc_msg?;
+            }, if stream.is_some() => {
+                let reason = match irc_msg {
+                    Some(Ok(msg)) => Ok(msg),
+                    Some(Err(e)) => Err(server_error.take().unwrap_or_else(|| e.to_string())),
+                    None => Err(server_error.take().unwrap_or_else(|| "the server closed the connection".to_string())),
+                };
+                let msg = match reason {
+                    Ok(msg) => msg,
+                    Err(reason) => lose_connection!(reason),
+                };
                 match &msg.command {
-                    Command::Response(Response::RPL_NAMREPLY, params) => {
-                        if params.len() >= 4 {
-                            let channel = params[2].clone();
-                            let names = parse_names(&params[3]);
+                    Command::Response(Response::RPL_WELCOME, params) => {
+                        // The server may have truncated or changed the nick we asked for
+                        if let Some(nick) = params.first() && nick != &current_nick {
+                            set_user_nick(nick).ok();
+                            current_nick = nick.clone();
+                        }
+                        let pending = queue.welcome();
+                        ui_tx.send(UiEvent::Connected {
+                            nick: current_nick.clone(),
+                            server_name: current_server_name.clone(),
+                        }).ok();
+                        if let Some(c) = &client {
+                            for (channel, key) in rejoin_channels.drain(..) {
+                                match &key {
+                                    Some(key) => try_send!(c.send_join_with_keys(&channel, key)),
+                                    None => try_send!(c.send_join(&channel)),
+                                }
+                                try_send!(c.send(Command::NAMES(Some(channel), None)));
+                            }
+                            for cmd in pending {
+                                try_send!(send_user_command(c, cmd, &mut current_channel, &current_nick, &current_username, &ui_tx));
+                            }
+                        }
+                    }
+                    Command::Response(Response::RPL_ISUPPORT, params) if params.len() >= 2 => {
+                        // <nick> <token>... :are supported by this server
+                        let had_monitor = isupport.monitor;
+                        isupport.apply(&params[1..params.len() - 1]);
+                        ui_tx.send(UiEvent::ISupport(isupport.clone())).ok();
+                        if !had_monitor && isupport.monitor && !notify_nicks.is_empty() && let Some(c) = &client {
+                            for targets in monitor_targets(&notify_nicks) {
+                                try_send!(c.send(Command::Raw("MONITOR".to_string(), vec!["+".to_string(), targets])));
+                            }
+                        }
+                    }
+                    // ISUPPORT is done by the end of the MOTD, so this is where a missing MONITOR shows
+                    Command::Response(Response::RPL_ENDOFMOTD | Response::ERR_NOMOTD, _) if !isupport.monitor && !notify_nicks.is_empty() => {
+                        ui_tx.send(UiEvent::Status {
+                            server_name: current_server_name.clone(),
+                            text: "This server has no MONITOR, the notify list won't report anyone".to_string(),
+                        }).ok();
+                    }
+                    // <nick> :alice!a@host,bob!b@host for online, plain nicks for offline
+                    Command::Response(resp @ (Response::RPL_MONONLINE | Response::RPL_MONOFFLINE), params) if params.len() >= 2 => {
+                        let online = *resp == Response::RPL_MONONLINE;
+                        for target in params[1].split(',').filter(|t| !t.is_empty()) {
+                            let nick = target.split('!').next().unwrap_or(target);
+                            ui_tx.send(UiEvent::Notify {
+                                server_name: current_server_name.clone(),
+                                nick: nick.to_string(),
+                                online,
+                            }).ok();
+                        }
+                    }
+                    Command::Response(Response::ERR_MONLISTFULL, params) if params.len() >= 3 => {
+                        ui_tx.send(UiEvent::Error(format!(
+                            "The server's MONITOR list is full, {} isn't being watched",
+                            params[2]
+                        ))).ok();
+                    }
+                    Command::Response(Response::RPL_NAMREPLY, params) if params.len() >= 4 => {
+                        // Big channels span several replies, wait for RPL_ENDOFNAMES
+                        pending_names
+                            .entry(params[2].clone())
+                            .or_default()
+                            .extend(parse_names(&params[3], &isupport.prefix_symbols()));
+                    }
+                    Command::Response(Response::RPL_ENDOFNAMES, params) if params.len() >= 2 => {
+                        let channel = params[1].clone();
+                        let mut names = pending_names.remove(&channel).unwrap_or_default();
+                        sort_members(&mut names, &isupport.prefix_symbols());
+                        channel_members.insert(channel.clone(), names.clone());
 
-                            ui_tx.send(UiEvent::ChannelUpdate {
+                        let count = names.len();
+                        ui_tx.send(UiEvent::ChannelUpdate {
+                            server_name: current_server_name.clone(),
+                            channel_name: channel.clone(),
+                            topic: None,
+                            client_count: count,
+                            clients: names,
+                            is_joined: true,
+                            is_dm: false,
+                        }).ok();
+                        if joining.remove(&channel) {
+                            ui_tx.send(UiEvent::Joined { server_name: current_server_name.clone(), channel, count }).ok();
+                        }
+                    }
+                    Command::Response(Response::ERR_BADCHANNELKEY, params) if params.len() >= 2 => {
+                        ui_tx.send(UiEvent::Error(format!(
+                            "Cannot join {}: it needs a key, use join {} <key>",
+                            params[1], params[1]
+                        ))).ok();
+                    }
+                    Command::Response(Response::RPL_TOPIC, params) if params.len() >= 3 => {
+                        ui_tx.send(UiEvent::Topic {
+                            server_name: current_server_name.clone(),
+                            channel: params[1].clone(),
+                            topic: Some(params[2].clone()),
+                            set_by: None,
+                            set_at: None,
+                        }).ok();
+                    }
+                    Command::Response(Response::RPL_TOPICWHOTIME, params) if params.len() >= 4 => {
+                        // <nick> <channel> <setter> <unix time>, the setter may be a full nick!user@host
+                        let set_by = params[2].split('!').next().unwrap_or(&params[2]).to_string();
+                        let set_at = params[3]
+                            .parse()
+                            .ok()
+                            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                            .map(|time| time.with_timezone(&Local));
+                        ui_tx.send(UiEvent::Topic {
+                            server_name: current_server_name.clone(),
+                            channel: params[1].clone(),
+                            topic: None,
+                            set_by: Some(set_by),
+                            set_at,
+                        }).ok();
+                    }
+                    Command::TOPIC(channel, Some(topic)) => {
+                        ui_tx.send(UiEvent::Topic {
+                            server_name: current_server_name.clone(),
+                            channel: channel.clone(),
+                            topic: Some(topic.clone()),
+                            set_by: msg.source_nickname().map(str::to_string),
+                            set_at: Some(message_time(&msg)),
+                        }).ok();
+                    }
+                    Command::CAP(_, sub, first, second) => {
+                        // "CAP * LS * :caps" marks a continued LS, the last line has no "*"
+                        let (more, caps) = match (first, second) {
+                            (Some(star), Some(caps)) if star == "*" => (true, caps.as_str()),
+                            (Some(caps), _) => (false, caps.as_str()),
+                            _ => (false, ""),
+                        };
+                        match sub {
+                            CapSubCommand::LS => {
+                                offered_caps.extend(caps.split_whitespace().map(|c| {
+                                    // 302 servers append values like sasl=PLAIN,EXTERNAL
+                                    c.split('=').next().unwrap_or(c).to_string()
+                                }));
+                                if !more && let Some(c) = &client {
+                                    let request: Vec<&str> = wanted_caps
+                                        .iter()
+                                        .filter(|w| offered_caps.contains(w))
+                                        .map(|w| w.as_str())
+                                        .collect();
+                                    if request.is_empty() {
+                                        try_send!(c.send(Command::CAP(None, CapSubCommand::END, None, None)));
+                                        cap_negotiating = false;
+                                    } else {
+                                        try_send!(c.send(Command::CAP(None, CapSubCommand::REQ, None, Some(request.join(" ")))));
+                                    }
+                                }
+                            }
+                            CapSubCommand::ACK => {
+                                for cap in caps.split_whitespace() {
+                                    match cap.strip_prefix('-') {
+                                        Some(removed) => enabled_caps.retain(|c| c != removed),
+                                        None => enabled_caps.push(cap.to_string()),
+                                    }
+                                }
+                                ui_tx.send(UiEvent::CapsChanged { caps: enabled_caps.clone() }).ok();
+                                if cap_negotiating && let Some(c) = &client {
+                                    try_send!(c.send(Command::CAP(None, CapSubCommand::END, None, None)));
+                                    cap_negotiating = false;
+                                }
+                            }
+                            CapSubCommand::NAK => {
+                                ui_tx.send(UiEvent::Status {
+                                    server_name: current_server_name.clone(),
+                                    text: format!("Server refused capabilities: {}", caps),
+                                }).ok();
+                                if cap_negotiating && let Some(c) = &client {
+                                    try_send!(c.send(Command::CAP(None, CapSubCommand::END, None, None)));
+                                    cap_negotiating = false;
+                                }
+                            }
+                            CapSubCommand::DEL => {
+                                enabled_caps.retain(|c| !caps.split_whitespace().any(|d| d == c));
+                                ui_tx.send(UiEvent::CapsChanged { caps: enabled_caps.clone() }).ok();
+                            }
+                            _ => {}
+                        }
+                    }
+                    Command::Response(Response::RPL_LOGGEDIN, _) | Command::Response(Response::RPL_SASLSUCCESS, _) => {
+                        identified = true;
+                        ui_tx.send(UiEvent::Identified {
+                            server_name: current_server_name.clone(),
+                            identified,
+                        }).ok();
+                    }
+                    Command::Response(Response::RPL_LOGGEDOUT, _) => {
+                        identified = false;
+                        ui_tx.send(UiEvent::Identified {
+                            server_name: current_server_name.clone(),
+                            identified,
+                        }).ok();
+                    }
+                    Command::NOTICE(_, text) if msg.source_nickname().is_some_and(|n| n.eq_ignore_ascii_case("NickServ")) => {
+                        ui_tx.send(UiEvent::Message(format!("-NickServ- {}", text))).ok();
+                        if is_identified_notice(text) && !identified {
+                            identified = true;
+                            ui_tx.send(UiEvent::Identified {
                                 server_name: current_server_name.clone(),
-                                channel_name: channel,
-                                topic: None,
-                                client_count: names.len(),
-                                clients: names,
-                                is_joined: true,
-                                is_dm: false,
+                                identified,
                             }).ok();
                         }
                     }
+                    Command::Response(Response::RPL_LIST, params) if params.len() >= 3 => {
+                        list_batch.push(ChannelListEntry {
+                            name: params[1].clone(),
+                            users: params[2].parse().unwrap_or(0),
+                            topic: params.get(3).cloned().unwrap_or_default(),
+                        });
+                        // Big networks send thousands of these, stream them in chunks
+                        if list_batch.len() >= LIST_BATCH_SIZE {
+                            ui_tx.send(UiEvent::ChannelList {
+                                entries: std::mem::take(&mut list_batch),
+                                done: false,
+                            }).ok();
+                        }
+                    }
+                    Command::Response(Response::RPL_LISTEND, _) => {
+                        ui_tx.send(UiEvent::ChannelList {
+                            entries: std::mem::take(&mut list_batch),
+                            done: true,
+                        }).ok();
+                    }
                     Command::PRIVMSG(target, text) => {
                         let nick = msg.source_nickname().unwrap_or("?");
-                        let is_dm = target == client
-                            .as_ref()
-                            .map(|c| c.current_nickname())
-                            .unwrap_or("");
-
-                        ui_tx.send(UiEvent::Message(format!(
-                            "<{}> {}",
-                            nick,
-                            text
-                        ))).ok();
+                        let is_dm = target.eq_ignore_ascii_case(&current_nick);
+
+                        // DMs live in a buffer named after the sender
+                        let channel = if is_dm { nick.to_string() } else { target.clone() };
                         if is_dm {
                             ui_tx.send(UiEvent::ChannelUpdate {
                                 server_name: current_server_name.clone(),
                                 channel_name: nick.to_string(),
                                 topic: None,
                                 client_count: 1,
-                                clients: vec![nick.to_string()],
+                                clients: vec![(None, nick.to_string())],
                                 is_joined: true,
                                 is_dm: true,
                             }).ok();
                         }
+                        ui_tx.send(UiEvent::PrivMsg {
+                            server_name: current_server_name.clone(),
+                            channel,
+                            nick: nick.to_string(),
+                            text: text.clone(),
+                            time: message_time(&msg),
+                            tags: message_tags(&msg),
+                        }).ok();
+                    }
+
+                    // The irc crate doesn't know REDACT, it arrives as a raw command
+                    Command::Raw(cmd, params) if cmd == "REDACT" && params.len() >= 2 => {
+                        // Like PRIVMSG, a redacted DM lives in the sender's buffer
+                        let target = &params[0];
+                        let channel = if target.eq_ignore_ascii_case(&current_nick) {
+                            msg.source_nickname().unwrap_or(target).to_string()
+                        } else {
+                            target.clone()
+                        };
+                        ui_tx.send(UiEvent::Redact {
+                            server_name: current_server_name.clone(),
+                            channel,
+                            msgid: params[1].clone(),
+                        }).ok();
                     }
 
                     Command::JOIN(channel, _, _) => {
                         if let Some(nick) = msg.source_nickname() {
-                            ui_tx.send(UiEvent::Message(format!("{} joined {}", nick, channel))).ok();
+                            if nick.eq_ignore_ascii_case(&current_nick) {
+                                joining.insert(channel.clone());
+                            }
+                            ui_tx.send(UiEvent::Presence {
+                                server_name: current_server_name.clone(),
+                                channel: Some(channel.clone()),
+                                kind: PresenceKind::Join,
+                                text: format!("{} joined {}", nick, channel),
+                                time: message_time(&msg),
+                            }).ok();
                             if channel == &current_channel && let Some(c) = &client {
                                 c.send(Command::NAMES(Some(channel.clone()), None)).ok();
                             }
@@ -249,7 +980,13 @@ pub async fn run_irc(
 
                     Command::PART(channel, _) => {
                         if let Some(nick) = msg.source_nickname() {
-                            ui_tx.send(UiEvent::Message(format!("{} left {}", nick, channel))).ok();
+                            ui_tx.send(UiEvent::Presence {
+                                server_name: current_server_name.clone(),
+                                channel: Some(channel.clone()),
+                                kind: PresenceKind::Part,
+                                text: format!("{} left {}", nick, channel),
+                                time: message_time(&msg),
+                            }).ok();
                             if channel == &current_channel && let Some(c) = &client {
                                 c.send(Command::NAMES(Some(channel.clone()), None)).ok();
                             }
@@ -258,15 +995,134 @@ pub async fn run_irc(
 
                     Command::QUIT(_) => {
                         if let Some(nick) = msg.source_nickname() {
-                            ui_tx.send(UiEvent::Message(format!("{} quit", nick))).ok();
+                            ui_tx.send(UiEvent::Presence {
+                                server_name: current_server_name.clone(),
+                                channel: None,
+                                kind: PresenceKind::Quit,
+                                text: format!("{} quit", nick),
+                                time: message_time(&msg),
+                            }).ok();
                             if !current_channel.is_empty() && let Some(c) = &client{
                                 c.send(Command::NAMES(Some(current_channel.clone()), None)).ok();
                             }
                         }
                     }
 
+                    Command::NICK(new) => {
+                        if let Some(old) = msg.source_nickname() {
+                            let is_self = old.eq_ignore_ascii_case(&current_nick);
+                            // Also covers services renaming us (GHOST/REGAIN, enforcement)
+                            if is_self {
+                                current_nick = new.clone();
+                                set_user_nick(new).ok();
+                            }
+
+                            let prefixes = isupport.prefix_symbols();
+                            let mut channels = Vec::new();
+                            for (channel, members) in channel_members.iter_mut() {
+                                let Some(member) = members.iter_mut().find(|m| m.1.eq_ignore_ascii_case(old)) else {
+                                    continue;
+                                };
+                                member.1 = new.clone();
+                                sort_members(members, &prefixes);
+                                channels.push(channel.clone());
+
+                                ui_tx.send(UiEvent::ChannelUpdate {
+                                    server_name: current_server_name.clone(),
+                                    channel_name: channel.clone(),
+                                    topic: None,
+                                    client_count: members.len(),
+                                    clients: members.clone(),
+                                    is_joined: true,
+                                    is_dm: false,
+                                }).ok();
+                            }
+
+                            ui_tx.send(UiEvent::NickChange {
+                                server_name: current_server_name.clone(),
+                                old: old.to_string(),
+                                new: new.clone(),
+                                channels,
+                                is_self,
+                            }).ok();
+                        }
+                    }
+
+                    Command::ChannelMODE(channel, modes) => {
+                        let setter = msg.source_nickname().unwrap_or(&current_server_name).to_string();
+                        let modes_text = modes.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ");
+                        ui_tx.send(UiEvent::ChannelMode {
+                            server_name: current_server_name.clone(),
+                            channel: channel.clone(),
+                            text: format!("{} sets {}", setter, modes_text),
+                        }).ok();
+
+                        // Only the highest prefix is kept, so losing it needs a fresh NAMES to find the next one
+                        let prefixes = isupport.prefix_symbols();
+                        let mut refresh = false;
+                        if let Some(members) = channel_members.get_mut(channel) {
+                            let mut changed = false;
+                            for mode in modes {
+                                let (adding, flag, nick) = match mode {
+                                    Mode::Plus(flag, Some(nick)) => (true, flag, nick),
+                                    Mode::Minus(flag, Some(nick)) => (false, flag, nick),
+                                    _ => continue,
+                                };
+                                let Some(symbol) = flag.to_string().chars().next().and_then(|c| isupport.prefix_symbol(c)) else {
+                                    continue;
+                                };
+                                let Some(member) = members.iter_mut().find(|m| m.1.eq_ignore_ascii_case(nick)) else {
+                                    continue;
+                                };
+                                if adding && prefix_rank(Some(symbol), &prefixes) < prefix_rank(member.0, &prefixes) {
+                                    member.0 = Some(symbol);
+                                    changed = true;
+                                } else if !adding && member.0 == Some(symbol) {
+                                    member.0 = None;
+                                    changed = true;
+                                    refresh = true;
+                                }
+                            }
+                            if changed {
+                                sort_members(members, &prefixes);
+                                ui_tx.send(UiEvent::ChannelUpdate {
+                                    server_name: current_server_name.clone(),
+                                    channel_name: channel.clone(),
+                                    topic: None,
+                                    client_count: members.len(),
+                                    clients: members.clone(),
+                                    is_joined: true,
+                                    is_dm: false,
+                                }).ok();
+                            }
+                        }
+                        if refresh && let Some(c) = &client {
+                            c.send(Command::NAMES(Some(channel.clone()), None)).ok();
+                        }
+                    }
+
+                    Command::UserMODE(target, modes) if target.eq_ignore_ascii_case(&current_nick) => {
+                        let modes_text = modes.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ");
+                        ui_tx.send(UiEvent::Status {
+                            server_name: current_server_name.clone(),
+                            text: format!("Your user modes: {}", modes_text),
+                        }).ok();
+                    }
+
+                    // K-lines, bans and flood kills all end this way
+                    Command::ERROR(reason) => {
+                        ui_tx.send(UiEvent::Status {
+                            server_name: current_server_name.clone(),
+                            text: format!("✖ Server error: {}", reason),
+                        }).ok();
+                        server_error = Some(reason.clone());
+                    }
+
                     Command::NAMES(_, Some(names_str)) => {
-                        let clients = parse_names(names_str);
+                        let prefixes = isupport.prefix_symbols();
+                        let mut clients = parse_names(names_str, &prefixes);
+                        sort_members(&mut clients, &prefixes);
+                        channel_members.insert(current_channel.clone(), clients.clone());
                         // Send ChannelUpdate with actual count
                         ui_tx.send(UiEvent::ChannelUpdate {
                             server_name: current_server_name.clone(),
@@ -286,6 +1142,64 @@ pub async fn run_irc(
     }
 }
 
+// Comma separated nick lists, each short enough that "MONITOR + <list>\r\n" fits in 512 bytes
+fn monitor_targets(nicks: &[String]) -> Vec<String> {
+    let room = MAX_LINE_LEN - "MONITOR + \r\n".len();
+    let mut batches: Vec<String> = Vec::new();
+    for nick in nicks {
+        match batches.last_mut() {
+            Some(batch) if batch.len() + 1 + nick.len() <= room => {
+                batch.push(',');
+                batch.push_str(nick);
+            }
+            _ => batches.push(nick.clone()),
+        }
+    }
+    batches
+}
+
+// Bytes of text a PRIVMSG to target can carry. Others receive it as
+// ":nick!user@host PRIVMSG target :text\r\n", which has to fit in 512 bytes
+fn privmsg_room(nick: &str, username: &str, target: &str) -> usize {
+    let overhead = format!(":{}!{}@ PRIVMSG {} :\r\n", nick, username, target).len() + MAX_HOST_LEN;
+    MAX_LINE_LEN.saturating_sub(overhead)
+}
+
+// Join, PrivMsg and Nick, sent straight away or replayed from the pre-registration queue
+fn send_user_command(
+    c: &Client,
+    cmd: IrcCommand,
+    current_channel: &mut String,
+    current_nick: &str,
+    current_username: &str,
+    ui_tx: &mpsc::UnboundedSender<UiEvent>,
+) -> Result<()> {
+    match cmd {
+        IrcCommand::Join { channel, key } => {
+            match &key {
+                Some(key) => c.send_join_with_keys(&channel, key)?,
+                None => c.send_join(&channel)?,
+            }
+            c.send(Command::NAMES(Some(channel.clone()), None))?;
+            *current_channel = channel;
+        }
+        IrcCommand::PrivMsg(msg) => {
+            if current_channel.is_empty() {
+                ui_tx.send(UiEvent::Error("No channel joined".to_string())).ok();
+            } else {
+                for chunk in split_message(&msg, privmsg_room(current_nick, current_username, current_channel)) {
+                    c.send_privmsg(&*current_channel, chunk)?;
+                }
+            }
+        }
+        IrcCommand::Nick(nick) => {
+            c.send(Command::NICK(nick.clone()))?;
+            set_user_nick(&nick).ok();
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
 pub fn get_config_dir() -> PathBuf {
     if let Some(home) = home_dir() {
@@ -349,13 +1263,101 @@ pub fn set_user_nick(nick: &str) -> Result<()> {
     Ok(())
 }
 
-fn parse_names(names_str: &str) -> Vec<String> {
+// IRCv3 server-time: bouncers replaying backlog tag each line with when it was really sent
+fn message_time(msg: &Message) -> DateTime<Local> {
+    msg.tags
+        .iter()
+        .flatten()
+        .find(|tag| tag.0 == "time")
+        .and_then(|tag| tag.1.as_deref())
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Local))
+        .unwrap_or_else(Local::now)
+}
+
+// IRCv3 message-tags: the server's id for this message and the one it replies to
+fn message_tags(msg: &Message) -> MessageTags {
+    let tag = |name: &str| {
+        msg.tags
+            .iter()
+            .flatten()
+            .find(|tag| tag.0 == name)
+            .and_then(|tag| tag.1.clone())
+    };
+    MessageTags {
+        msgid: tag("msgid"),
+        reply_to: tag("+draft/reply"),
+    }
+}
+
+// Cut text into pieces of at most max_bytes, preferring spaces and never splitting a character
+fn split_message(text: &str, max_bytes: usize) -> Vec<&str> {
+    let max_bytes = max_bytes.max(4); // room for any single UTF-8 character
+    let mut chunks = Vec::new();
+    let mut rest = text;
+
+    while rest.len() > max_bytes {
+        let mut end = max_bytes;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Break at the last space if there is one (dropping it), otherwise mid-word
+        let mut next = end;
+        if let Some(space) = rest[..end].rfind(' ') && space > 0 {
+            end = space;
+            next = space + 1;
+        }
+        chunks.push(&rest[..end]);
+        rest = &rest[next..];
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+// NickServ wording differs between services packages
+fn is_identified_notice(text: &str) -> bool {
+    let text = text.to_lowercase();
+    text.contains("you are now identified")
+        || text.contains("you are successfully identified")
+        || text.contains("password accepted")
+}
+
+// With multi-prefix a nick can carry several prefixes ("@+duck"), keep the highest.
+// prefixes are the server's membership symbols from highest to lowest rank
+fn parse_names(names_str: &str, prefixes: &[char]) -> Vec<(Option<char>, String)> {
     names_str
         .split_whitespace()
-        .map(|s| s.trim_start_matches('@').trim_start_matches('+').to_string())
+        .map(|s| {
+            let prefix = s.chars().next().filter(|c| prefixes.contains(c));
+            (prefix, s.trim_start_matches(prefixes).to_string())
+        })
         .collect()
 }
 
+// Sort key putting ops above voiced users above everyone else
+fn prefix_rank(prefix: Option<char>, prefixes: &[char]) -> usize {
+    prefix
+        .and_then(|p| prefixes.iter().position(|&c| c == p))
+        .unwrap_or(prefixes.len())
+}
+
+// Drop duplicate nicks (keeping the highest prefix), then sort by rank and name
+fn sort_members(names: &mut Vec<(Option<char>, String)>, prefixes: &[char]) {
+    names.sort_by(|a, b| {
+        a.1.to_lowercase()
+            .cmp(&b.1.to_lowercase())
+            .then_with(|| prefix_rank(a.0, prefixes).cmp(&prefix_rank(b.0, prefixes)))
+    });
+    names.dedup_by(|later, earlier| later.1.eq_ignore_ascii_case(&earlier.1));
+    names.sort_by(|a, b| {
+        prefix_rank(a.0, prefixes)
+            .cmp(&prefix_rank(b.0, prefixes))
+            .then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase()))
+    });
+}
+
 fn create_default_runtime_config(path: &PathBuf) -> Result<()> {
     let default_config = r##"nickname = "duck"
 nick_password = "duck"
@@ -401,3 +1403,113 @@ channels = ["#TPP"]"##;
     fs::write(path, default_config)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Byte offset of a chunk inside the text it was cut from
+    fn offset_in(text: &str, chunk: &str) -> usize {
+        chunk.as_ptr() as usize - text.as_ptr() as usize
+    }
+
+    fn assert_chunks_fit(text: &str, chunks: &[&str], max_bytes: usize) {
+        for chunk in chunks {
+            assert!(chunk.len() <= max_bytes, "{} byte chunk over {}", chunk.len(), max_bytes);
+            let start = offset_in(text, chunk);
+            assert!(text.is_char_boundary(start) && text.is_char_boundary(start + chunk.len()));
+        }
+    }
+
+    #[test]
+    fn split_message_ascii_breaks_at_spaces() {
+        let text = "quack ".repeat(333) + "qu"; // 2000 bytes
+        let chunks = split_message(&text, 400);
+        assert!(chunks.len() > 1);
+        assert_chunks_fit(&text, &chunks, 400);
+        // Whole words only, the space at each break is the one dropped
+        assert!(chunks.iter().all(|c| c.split(' ').all(|w| w == "quack" || w == "qu")));
+        assert_eq!(chunks.join(" "), text);
+    }
+
+    #[test]
+    fn split_message_multibyte_without_spaces() {
+        for text in ["é".repeat(1000), "🦆".repeat(500)] {
+            let chunks = split_message(&text, 399);
+            assert!(chunks.len() > 1);
+            assert_chunks_fit(&text, &chunks, 399);
+            assert_eq!(chunks.concat(), text);
+        }
+    }
+
+    #[test]
+    fn split_message_multibyte_with_spaces() {
+        let text = "🦆é ".repeat(285); // 2000 bytes
+        let chunks = split_message(&text, 300);
+        assert_chunks_fit(&text, &chunks, 300);
+        assert!(chunks.iter().all(|c| c.split(' ').all(|w| w == "🦆é" || w.is_empty())));
+        assert_eq!(chunks.join(" "), text);
+    }
+
+    #[test]
+    fn split_message_short_text_is_one_chunk() {
+        assert_eq!(split_message("hello", 400), vec!["hello"]);
+        assert_eq!(split_message("", 400), vec![""]);
+    }
+
+    #[test]
+    fn commands_before_welcome_are_queued() {
+        let mut queue = CommandQueue::default();
+        assert!(queue.push(IrcCommand::Join { channel: "#duck".to_string(), key: None }));
+        assert!(!queue.push(IrcCommand::PrivMsg("quack".to_string())));
+        assert!(!queue.push(IrcCommand::Nick("mallard".to_string())));
+        assert!(!queue.registered);
+        assert_eq!(queue.pending.len(), 3);
+    }
+
+    #[test]
+    fn welcome_replays_queued_commands_in_order() {
+        let mut queue = CommandQueue::default();
+        queue.push(IrcCommand::Join { channel: "#duck".to_string(), key: None });
+        queue.push(IrcCommand::PrivMsg("quack".to_string()));
+        queue.push(IrcCommand::Nick("mallard".to_string()));
+        let replayed = queue.welcome();
+        assert!(queue.registered);
+        assert!(queue.pending.is_empty());
+        assert_eq!(replayed.len(), 3);
+        assert!(matches!(&replayed[0], IrcCommand::Join { channel, key: None } if channel == "#duck"));
+        assert!(matches!(&replayed[1], IrcCommand::PrivMsg(text) if text == "quack"));
+        assert!(matches!(&replayed[2], IrcCommand::Nick(nick) if nick == "mallard"));
+    }
+
+    #[test]
+    fn disconnect_drops_queued_commands() {
+        let mut queue = CommandQueue::default();
+        queue.push(IrcCommand::Join { channel: "#duck".to_string(), key: None });
+        queue.reset();
+        assert!(!queue.registered);
+        assert!(queue.welcome().is_empty());
+
+        queue.reset();
+        assert!(!queue.registered);
+        assert!(queue.push(IrcCommand::PrivMsg("quack".to_string())));
+    }
+
+    #[test]
+    fn monitor_targets_stay_under_the_line_limit() {
+        let nicks: Vec<String> = (0..200).map(|i| format!("duckling{:03}", i)).collect();
+        let batches = monitor_targets(&nicks);
+        assert!(batches.len() > 1);
+        for batch in &batches {
+            assert!(format!("MONITOR + {}\r\n", batch).len() <= MAX_LINE_LEN);
+        }
+        assert_eq!(batches.join(","), nicks.join(","));
+    }
+
+    #[test]
+    fn monitor_targets_short_list_is_one_line() {
+        let nicks = vec!["mallard".to_string(), "teal".to_string()];
+        assert_eq!(monitor_targets(&nicks), vec!["mallard,teal"]);
+        assert!(monitor_targets(&[]).is_empty());
+    }
+}
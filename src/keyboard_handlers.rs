@@ -1,13 +1,51 @@
-use crate::irc::{IrcCommand, get_user_nick};
-use crate::app::{App, VimMode, ChannelContext};
-use crate::ServerTreeItem;
+use crate::irc::IrcCommand;
+use crate::app::{App, VimMode};
 use tokio::sync::mpsc;
 use ratatui::crossterm::event;
 use crossterm::event::KeyEvent;
 
 pub fn handle_keyboard_event(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>,) {
+    app.end_replace_outside_insert();
+
+    // The quit prompt takes the next key whatever the mode, anything but y keeps the message
+    if app.quit_confirm {
+        app.quit_confirm = false;
+        if matches!(key.code, event::KeyCode::Char('y') | event::KeyCode::Char('Y')) {
+            app.should_quit = true;
+        }
+        return;
+    }
+
+    // Channel switching works from every mode that isn't a popup or the command line
+    if key.modifiers.contains(event::KeyModifiers::ALT)
+        && matches!(key.code, event::KeyCode::Left | event::KeyCode::Right)
+        && !matches!(app.vim_mode, VimMode::Command | VimMode::Help | VimMode::ChannelList)
+    {
+        app.cycle_channel(key.code == event::KeyCode::Right, irc_tx);
+        return;
+    }
+    if key.modifiers.contains(event::KeyModifiers::ALT)
+        && key.code == event::KeyCode::Char('a')
+        && !matches!(app.vim_mode, VimMode::Command | VimMode::Help | VimMode::ChannelList)
+    {
+        app.jump_to_unread(irc_tx);
+        return;
+    }
+    match key.code {
+        event::KeyCode::F(3) => {
+            app.pin_servers = !app.pin_servers;
+            app.rebuild_server_tree();
+            return;
+        }
+        event::KeyCode::F(4) => {
+            app.pin_clients = !app.pin_clients;
+            return;
+        }
+        _ => {}
+    }
+
     match app.vim_mode {
-        VimMode::Normal => {handle_normal(key, app);},
+        VimMode::Normal => {handle_normal(key, app, irc_tx);},
         VimMode::Insert => {handle_insert(key, app, irc_tx);},
         VimMode::Visual => {handle_visual(key, app);},
         VimMode::Command => {handle_command(key, app, irc_tx);},
@@ -15,11 +53,28 @@ pub fn handle_keyboard_event(key: KeyEvent, app: &mut App, irc_tx: &mpsc::Unboun
         VimMode::Messages => {handle_messages(key, app);},
         VimMode::Clients => {handle_clients(key, app, irc_tx);},
         VimMode::Vimless => {handle_vimless(key, app, irc_tx);}
+        VimMode::Help => {handle_help(key, app);}
+        VimMode::ChannelList => {handle_channel_list(key, app, irc_tx);}
     }
 }
 
-fn handle_normal(key: KeyEvent, app: &mut App, ) {
+fn handle_normal(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>,) {
     match key.code {
+        // Send and start over, o keeps typing in Insert mode while O stays in Normal mode
+        event::KeyCode::Char(c @ ('o' | 'O')) if app.get_norm_text().is_empty() => {
+            app.send_msg_lines(irc_tx);
+            if c == 'o' {
+                app.vim_mode = VimMode::Insert;
+                app.prev_mode = Some(VimMode::Normal);
+            }
+        }
+        // Read back through the scrollback without leaving the input
+        event::KeyCode::PageUp => {
+            app.scroll_msgs_up(app.page_size());
+        }
+        event::KeyCode::PageDown => {
+            app.scroll_msgs_down(app.page_size());
+        }
         event::KeyCode::Tab => {
             app.rebuild_server_tree();
             app.cycle_mode();
@@ -48,23 +103,65 @@ fn handle_normal(key: KeyEvent, app: &mut App, ) {
     }
 }
 
+// Up/Down pick a nick, Enter takes it and Esc closes the popup, the rest goes on to the mode.
+// A popup that opened while typing leaves all of them alone until Tab picks it up
+fn handle_nick_popup(key: KeyEvent, app: &mut App) -> bool {
+    if !app.nick_popup.as_ref().is_some_and(|p| p.active) {
+        return false;
+    }
+    match key.code {
+        event::KeyCode::Up => app.move_nick_popup(false),
+        event::KeyCode::Down => app.move_nick_popup(true),
+        event::KeyCode::Enter => app.accept_nick_popup(),
+        event::KeyCode::Esc => app.nick_popup = None,
+        _ => return false,
+    }
+    true
+}
+
 fn handle_insert(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>,) {
+    if handle_nick_popup(key, app) {
+        return;
+    }
     match key.code {
+        // Tab completes a nick, or leaves Insert mode when there is none to complete
+        event::KeyCode::Tab if app.complete_nick() => {}
         event::KeyCode::Tab => {
+            app.overwrite = false;
             app.rebuild_server_tree();
             app.cycle_mode();
         }
         event::KeyCode::Esc => {
+            app.paste_confirm = None;
+            app.overwrite = false;
             app.vim_mode = VimMode::Normal;
             app.prev_mode = Some(VimMode::Insert);
-            //move cursor backwards if necessary
-            if app.msg_cursor == app.msg.len() {
-                app.move_msg_cursor_left();
-            }
+            // Like vim, leaving Insert steps back onto the last typed character
+            app.move_msg_cursor_left();
+        }
+        event::KeyCode::Char('v') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.paste_clipboard();
+        }
+        event::KeyCode::Char('w') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.delete_msg_word_back();
+        }
+        event::KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.delete_msg_to_start();
+        }
+        event::KeyCode::Char('k') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.delete_msg_to_end();
+        }
+        event::KeyCode::Char(c) if app.overwrite => {
+            app.replace_msg_char(c);
+            app.msg_cursor += 1;
         }
         event::KeyCode::Char(c) => {
             app.insert_msg_char(c);
         }
+        // Replace mode only steps back, the overwritten text isn't kept around to restore
+        event::KeyCode::Backspace if app.overwrite => {
+            app.move_msg_cursor_left();
+        }
         event::KeyCode::Backspace => {
             app.delete_msg_char();
         }
@@ -74,19 +171,16 @@ fn handle_insert(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<Ir
         event::KeyCode::Right => {
             app.move_msg_cursor_right();
         }
+        // Shift-Enter needs a terminal that reports it, Alt-Enter works nearly everywhere
+        event::KeyCode::Enter if key.modifiers.intersects(event::KeyModifiers::ALT | event::KeyModifiers::SHIFT) => {
+            app.insert_msg_char('\n');
+        }
         event::KeyCode::Enter => {
-            let msg = app.take_msg_text();
-            if !msg.is_empty() {
-                // Send to IRC
-                irc_tx.send(IrcCommand::PrivMsg(msg.clone())).ok();
-                // Echo locally
-                let nick = get_user_nick().unwrap_or("guest".to_string());
-                app.push_user_msg_to_current(&nick, &msg);
-            }
-            app.msg_cursor = 0;
+            app.send_msg_lines(irc_tx);
         }
         _ => {}
     }
+    app.refresh_nick_popup();
 }
 
 fn handle_visual(key: KeyEvent, app: &mut App, ) {
@@ -138,14 +232,46 @@ fn handle_command(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<I
         event::KeyCode::Enter => {
             let cmd = app.take_cmd_text();
             app.execute_command(&cmd, irc_tx);
-            app.return_to_prev_mode();
+            // commands like help switch mode themselves
+            if app.vim_mode == VimMode::Command {
+                app.return_to_prev_mode();
+            }
         }
         _ => {}
     }
 }
 
 fn handle_server(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>) {
+    // While filtering, typing goes to the filter and Esc drops it
+    if app.server_filter.is_some() {
+        match key.code {
+            event::KeyCode::Esc => {
+                app.clear_server_filter();
+            }
+            event::KeyCode::Backspace => {
+                app.pop_server_filter();
+            }
+            event::KeyCode::Down => {
+                app.move_server_selection_down();
+            }
+            event::KeyCode::Up => {
+                app.move_server_selection_up();
+            }
+            event::KeyCode::Enter => {
+                app.activate_server_tree_item(app.server_tree_index, irc_tx);
+            }
+            event::KeyCode::Char(c) => {
+                app.push_server_filter(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
     match key.code {
+        event::KeyCode::Char('/') => {
+            app.start_server_filter();
+        }
         event::KeyCode::Tab => {
             app.cycle_mode();
         }
@@ -179,70 +305,13 @@ fn handle_server(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<Ir
             app.prev_mode = Some(VimMode::Server);
         }
         event::KeyCode::Enter => {
-            if let Some(tree_index) = Some(app.server_tree_index) && let Some(item) = app.server_tree.get(tree_index) {
-                match item {
-                    ServerTreeItem::Server { server_idx } => {
-                        let server_idx_copy = *server_idx;
-                        let server = &app.servers[server_idx_copy];
-                        let server_name = server.name.clone();
-
-                        if app.is_server_connected(server_idx_copy) {
-                            irc_tx.send(IrcCommand::Disconnect).ok();
-                            app.push_system_to_current(format!("Disconnecting from {}...", server_name));
-                            
-                            app.current_channel = None;
-                            app.channel.clear();
-                        } else {
-                            // Disconnect any currently connected server
-                            irc_tx.send(IrcCommand::Disconnect).ok();
-
-                            // Connect to this server
-                            irc_tx.send(IrcCommand::Connect(server_name.clone())).ok();
-                            app.push_system_to_current(format!("Connecting to {}...", server_name));
-                            
-                            app.current_channel = Some(ChannelContext {
-                                server_name: server_name.clone(),
-                                channel_name: "status".to_string(),
-                            });
-
-                            app.channel_messages
-                                .entry((server_name.clone(), "status".to_string()))
-                                .or_default();
-                        }
-
-                        app.toggle_server_expansion(server_idx_copy);
-                    }
-                    ServerTreeItem::Channel { server_idx, channel_idx } => {
-                        let server = &app.servers[*server_idx];
-                        let channel = &server.channels[*channel_idx];
-                        let channel_name = channel.name.clone();
-
-                        // Auto-join the channel if connected to server
-                        if app.is_server_connected(*server_idx) {
-                            irc_tx.send(IrcCommand::Join(channel_name.clone())).ok();
-                            
-                            app.current_channel = Some(ChannelContext {
-                                server_name: server.name.clone(),
-                                channel_name: channel_name.clone(),
-                            });
-
-                            irc_tx.send(IrcCommand::SetCurrentChannel(channel_name.clone())).ok();
-                            
-                            // Initialize messages for this channel if needed
-                            app.channel_messages
-                                .entry((server.name.clone(), channel_name.clone()))
-                                .or_default();
-                            
-                            app.channel = channel_name.clone();
-                        } else {
-                            app.push_system_to_current(format!(
-                                "Not connected to server {}. Connect first.",
-                                server.name
-                            ));
-                        }
-                    }
-                }
-            }
+            app.activate_server_tree_item(app.server_tree_index, irc_tx);
+        }
+        event::KeyCode::Char('<') => {
+            app.resize_panel(true, false);
+        }
+        event::KeyCode::Char('>') => {
+            app.resize_panel(true, true);
         }
         _ => {}
     }
@@ -251,11 +320,16 @@ fn handle_server(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<Ir
 fn handle_messages(key: KeyEvent, app: &mut App, ) {
     match key.code {
         event::KeyCode::Tab => {
+            app.msg_sel_start = None;
             app.cycle_mode();
         }
         event::KeyCode::Esc => {
-            app.vim_mode = VimMode::Normal;
-            app.prev_mode = Some(VimMode::Messages);
+            app.clear_messages_cmd();
+            // Like vim, the first Esc only drops the V selection
+            if app.msg_sel_start.take().is_none() {
+                app.vim_mode = VimMode::Normal;
+                app.prev_mode = Some(VimMode::Messages);
+            }
         }
         event::KeyCode::Down => {
             app.move_msg_down();
@@ -263,6 +337,21 @@ fn handle_messages(key: KeyEvent, app: &mut App, ) {
         event::KeyCode::Up => {
             app.move_msg_up();
         }
+        event::KeyCode::PageUp => {
+            app.scroll_msgs_up(app.page_size());
+        }
+        event::KeyCode::PageDown => {
+            app.scroll_msgs_down(app.page_size());
+        }
+        // vim's half-page scrolls, always half the screen whatever page_scroll_lines says
+        event::KeyCode::Char('d') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.clear_messages_cmd();
+            app.scroll_msgs_down(app.half_page_size());
+        }
+        event::KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.clear_messages_cmd();
+            app.scroll_msgs_up(app.half_page_size());
+        }
         event::KeyCode::Char(c) => {
             app.push_char_to_messages_cmd(c);
             app.execute_messages_cmd();
@@ -272,7 +361,44 @@ fn handle_messages(key: KeyEvent, app: &mut App, ) {
 }
 
 fn handle_clients(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>,) {
+    // Same as the server tree filter, typing narrows the list until Esc
+    if app.client_filter.is_some() {
+        match key.code {
+            event::KeyCode::Esc => {
+                app.clear_client_filter();
+            }
+            event::KeyCode::Backspace => {
+                app.pop_client_filter();
+            }
+            event::KeyCode::Down => {
+                app.move_client_selection_down();
+            }
+            event::KeyCode::Up => {
+                app.move_client_selection_up();
+            }
+            event::KeyCode::Enter => {
+                app.join_selected_client_channel(irc_tx);
+                app.rebuild_server_tree();
+            }
+            event::KeyCode::Char(c) => {
+                app.push_client_filter(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
     match key.code {
+        event::KeyCode::Char('/') if app.clients_cmd.is_empty() => {
+            app.start_client_filter();
+        }
+        // The panel grows leftwards, so > widens it like in Server mode
+        event::KeyCode::Char('<') if app.clients_cmd.is_empty() => {
+            app.resize_panel(false, false);
+        }
+        event::KeyCode::Char('>') if app.clients_cmd.is_empty() => {
+            app.resize_panel(false, true);
+        }
         event::KeyCode::Tab => {
             app.cycle_mode();
         }
@@ -299,9 +425,39 @@ fn handle_clients(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<I
 }
 
 fn handle_vimless(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>,) {
+    if handle_nick_popup(key, app) {
+        return;
+    }
     match key.code {
+        event::KeyCode::Tab => {
+            app.complete_nick();
+        }
+        event::KeyCode::Enter if key.modifiers.intersects(event::KeyModifiers::ALT | event::KeyModifiers::SHIFT) => {
+            app.insert_msg_char('\n');
+        }
         event::KeyCode::Enter => {
-            app.execute_vimless(irc_tx);
+            app.remember_input();
+            // Multi-line input is a message, never a /command
+            if app.get_msg_iter().any(|c| c == '\n') {
+                app.send_msg_lines(irc_tx);
+            } else {
+                app.execute_vimless(irc_tx);
+            }
+        }
+        event::KeyCode::Esc => {
+            app.paste_confirm = None;
+        }
+        event::KeyCode::Char('v') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.paste_clipboard();
+        }
+        event::KeyCode::Char('w') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.delete_msg_word_back();
+        }
+        event::KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.delete_msg_to_start();
+        }
+        event::KeyCode::Char('k') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+            app.delete_msg_to_end();
         }
         event::KeyCode::Char(c) => {
             app.insert_msg_char(c);
@@ -316,11 +472,213 @@ fn handle_vimless(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<I
             app.delete_msg_char();
         }
         event::KeyCode::Up => {
-            app.move_msg_cursor_to_start();
+            app.input_history_prev();
         }
         event::KeyCode::Down => {
+            app.input_history_next();
+        }
+        event::KeyCode::Home => {
+            app.move_msg_cursor_to_start();
+        }
+        event::KeyCode::End => {
             app.move_msg_cursor_to_end();
         }
         _ => {}
     }
+    app.refresh_nick_popup();
+}
+
+fn handle_help(key: KeyEvent, app: &mut App) {
+    match key.code {
+        event::KeyCode::Esc | event::KeyCode::Char('q') => {
+            app.return_to_prev_mode();
+        }
+        event::KeyCode::Down | event::KeyCode::Char('j') => {
+            app.scroll_help_down();
+        }
+        event::KeyCode::Up | event::KeyCode::Char('k') => {
+            app.scroll_help_up();
+        }
+        _ => {}
+    }
+}
+
+fn handle_channel_list(key: KeyEvent, app: &mut App, irc_tx: &mpsc::UnboundedSender<IrcCommand>) {
+    // Letters go to the filter, so navigation sticks to arrows and pages
+    match key.code {
+        event::KeyCode::Esc => {
+            app.return_to_prev_mode();
+        }
+        event::KeyCode::Enter => {
+            app.join_selected_list_channel(irc_tx);
+        }
+        event::KeyCode::Down => {
+            app.move_channel_list_down(1);
+        }
+        event::KeyCode::Up => {
+            app.move_channel_list_up(1);
+        }
+        event::KeyCode::PageDown => {
+            app.move_channel_list_down(10);
+        }
+        event::KeyCode::PageUp => {
+            app.move_channel_list_up(10);
+        }
+        event::KeyCode::Backspace => {
+            app.pop_channel_list_filter();
+        }
+        event::KeyCode::Char(c) => {
+            app.push_channel_list_filter(c);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn normal_app(text: &str, from: usize) -> App {
+        let mut app = App::for_test(text, from);
+        app.vim_mode = VimMode::Normal;
+        app
+    }
+
+    // Every command the keys sent to the IRC task
+    fn press(app: &mut App, keys: &[KeyCode]) -> Vec<IrcCommand> {
+        let (irc_tx, mut irc_rx) = mpsc::unbounded_channel();
+        for code in keys {
+            handle_keyboard_event(KeyEvent::new(*code, KeyModifiers::NONE), app, &irc_tx);
+        }
+        std::iter::from_fn(|| irc_rx.try_recv().ok()).collect()
+    }
+
+    fn typed(text: &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    #[test]
+    fn a_at_the_end_of_the_line_then_esc_lands_on_the_last_inserted_char() {
+        let mut app = normal_app("foo", 2);
+        press(&mut app, &[KeyCode::Char('a')]);
+        assert_eq!(app.vim_mode, VimMode::Insert);
+        assert_eq!(app.msg_cursor, 3);
+        press(&mut app, &typed("bar"));
+        press(&mut app, &[KeyCode::Esc]);
+        assert_eq!(app.vim_mode, VimMode::Normal);
+        assert_eq!(app.get_msg_iter().collect::<String>(), "foobar");
+        assert_eq!(app.msg_cursor, 5);
+    }
+
+    #[test]
+    fn uppercase_a_then_esc_lands_on_the_last_inserted_char() {
+        let mut app = normal_app("foo bar", 0);
+        press(&mut app, &[KeyCode::Char('A')]);
+        assert_eq!(app.msg_cursor, 7);
+        press(&mut app, &typed("!!"));
+        press(&mut app, &[KeyCode::Esc]);
+        assert_eq!(app.get_msg_iter().collect::<String>(), "foo bar!!");
+        assert_eq!(app.msg_cursor, 8);
+    }
+
+    #[test]
+    fn a_on_an_empty_input_stays_in_range() {
+        let mut app = normal_app("", 0);
+        press(&mut app, &[KeyCode::Char('a')]);
+        assert_eq!(app.vim_mode, VimMode::Insert);
+        assert_eq!(app.msg_cursor, 0);
+        press(&mut app, &[KeyCode::Esc]);
+        assert_eq!(app.msg_cursor, 0);
+
+        press(&mut app, &[KeyCode::Char('a')]);
+        press(&mut app, &typed("x"));
+        press(&mut app, &[KeyCode::Esc]);
+        assert_eq!(app.get_msg_iter().collect::<String>(), "x");
+        assert_eq!(app.msg_cursor, 0);
+    }
+
+    fn insert_app_with_members() -> App {
+        let mut app = normal_app("", 0);
+        app.vim_mode = VimMode::Insert;
+        app.set_clients(vec![(None, "mallard".to_string()), (None, "mandarin".to_string()), (None, "teal".to_string())]);
+        app
+    }
+
+    #[test]
+    fn typing_a_prefix_of_several_nicks_opens_the_popup() {
+        let mut app = insert_app_with_members();
+        press(&mut app, &typed("hi m"));
+        assert_eq!(app.nick_popup.as_ref().map(|p| p.matches.clone()), Some(vec!["mallard".to_string(), "mandarin".to_string()]));
+
+        press(&mut app, &typed("al"));
+        assert_eq!(app.nick_popup.as_ref().map(|p| p.matches.clone()), Some(vec!["mallard".to_string()]));
+
+        press(&mut app, &typed(" "));
+        assert!(app.nick_popup.is_none());
+    }
+
+    #[test]
+    fn a_single_match_waits_for_tab() {
+        let mut app = insert_app_with_members();
+        press(&mut app, &typed("te"));
+        assert!(app.nick_popup.is_none());
+        press(&mut app, &[KeyCode::Tab]);
+        assert_eq!(app.get_msg_iter().collect::<String>(), format!("teal{}", app.config.ui.completion_suffix));
+    }
+
+    #[test]
+    fn enter_sends_while_the_popup_only_lists_matches() {
+        let mut app = insert_app_with_members();
+        press(&mut app, &typed("hi m"));
+        assert!(app.nick_popup.as_ref().is_some_and(|p| !p.active));
+        let sent = press(&mut app, &[KeyCode::Enter]);
+        assert!(matches!(sent.as_slice(), [IrcCommand::PrivMsg(text)] if text == "hi m"));
+        assert!(app.nick_popup.is_none());
+    }
+
+    #[test]
+    fn esc_leaves_insert_while_the_popup_only_lists_matches() {
+        let mut app = insert_app_with_members();
+        press(&mut app, &typed("m"));
+        press(&mut app, &[KeyCode::Esc]);
+        assert_eq!(app.vim_mode, VimMode::Normal);
+        assert!(app.nick_popup.is_none());
+    }
+
+    #[test]
+    fn tab_selects_in_the_popup_then_enter_inserts() {
+        let mut app = insert_app_with_members();
+        press(&mut app, &typed("hi m"));
+        press(&mut app, &[KeyCode::Tab, KeyCode::Down]);
+        let sent = press(&mut app, &[KeyCode::Enter]);
+        assert!(sent.is_empty());
+        assert_eq!(app.get_msg_iter().collect::<String>(), "hi mandarin ");
+    }
+
+    #[test]
+    fn editing_after_the_paste_prompt_asks_again() {
+        let mut app = insert_app_with_members();
+        app.config.ui.paste_confirm_lines = 1;
+        app.insert_msg_str("one\ntwo");
+        assert!(press(&mut app, &[KeyCode::Enter]).is_empty());
+        press(&mut app, &typed("!"));
+        assert!(press(&mut app, &[KeyCode::Enter]).is_empty());
+        let sent = press(&mut app, &[KeyCode::Enter]);
+        assert!(matches!(sent.as_slice(), [IrcCommand::PrivMsg(a), IrcCommand::PrivMsg(b)] if a == "one" && b == "two!"));
+    }
+
+    #[test]
+    fn replace_ends_when_insert_is_left_some_other_way() {
+        let mut app = normal_app("foo", 0);
+        press(&mut app, &[KeyCode::Char('R')]);
+        assert!(app.overwrite);
+        // As a mouse click or Alt-arrow would, without going through Esc
+        app.vim_mode = VimMode::Normal;
+        press(&mut app, &[KeyCode::Char('i')]);
+        assert_eq!(app.vim_mode, VimMode::Insert);
+        assert!(!app.overwrite);
+        press(&mut app, &typed("x"));
+        assert_eq!(app.get_msg_iter().collect::<String>(), "xfoo");
+    }
 }
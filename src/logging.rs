@@ -0,0 +1,133 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::irc::get_config_dir;
+
+// Lines are kept in memory and written out at most this often so the UI never waits on disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Timestamp written at the start of every log line
+pub const LOG_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// ~/.config/duckIRC/logs/<server>/<channel>.log
+pub fn log_path(server_name: &str, channel_name: &str) -> PathBuf {
+    get_config_dir()
+        .join("logs")
+        .join(sanitize(server_name))
+        .join(format!("{}.log", sanitize(channel_name)))
+}
+
+// Channel and server names may hold characters that mean something in a path
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c == '/' || c == '\\' || c == '\0' { '_' } else { c })
+        .collect()
+}
+
+// "[2024-05-01 13:37:00] <nick> text" for users, "[...] * text" for everything else
+pub fn format_line(time: DateTime<Local>, nick: Option<&str>, text: &str) -> String {
+    let text = text.replace('\n', " ");
+    match nick {
+        Some(nick) => format!("[{}] <{}> {}\n", time.format(LOG_TIME_FORMAT), nick, text),
+        None => format!("[{}] * {}\n", time.format(LOG_TIME_FORMAT), text),
+    }
+}
+
+// Undo format_line, None for lines that don't look like ours
+pub fn parse_line(line: &str) -> Option<(DateTime<Local>, Option<String>, String)> {
+    let (stamp, rest) = line.strip_prefix('[')?.split_once("] ")?;
+    let time = NaiveDateTime::parse_from_str(stamp, LOG_TIME_FORMAT)
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()?;
+
+    if let Some((nick, text)) = rest.strip_prefix('<').and_then(|r| r.split_once("> ")) {
+        Some((time, Some(nick.to_string()), text.to_string()))
+    } else {
+        Some((time, None, rest.strip_prefix("* ").unwrap_or(rest).to_string()))
+    }
+}
+
+// "~/notes.log" and the like, for paths typed into commands
+pub fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") && let Some(home) = dirs::home_dir() {
+        return home.join(rest);
+    }
+    PathBuf::from(path)
+}
+
+// The last count parsed lines of a channel's log, oldest first
+pub fn read_tail(server_name: &str, channel_name: &str, count: usize) -> Vec<(DateTime<Local>, Option<String>, String)> {
+    let Ok(contents) = fs::read_to_string(log_path(server_name, channel_name)) else {
+        return Vec::new();
+    };
+    let mut lines: Vec<_> = contents.lines().rev().filter_map(parse_line).take(count).collect();
+    lines.reverse();
+    lines
+}
+
+#[derive(Debug)]
+pub struct ChatLog {
+    pending: HashMap<PathBuf, String>,
+    last_flush: Instant,
+}
+
+impl Default for ChatLog {
+    fn default() -> Self {
+        Self {
+            pending: HashMap::new(),
+            last_flush: Instant::now(),
+        }
+    }
+}
+
+impl ChatLog {
+    pub fn append(&mut self, server_name: &str, channel_name: &str, line: String) {
+        self.pending
+            .entry(log_path(server_name, channel_name))
+            .or_default()
+            .push_str(&line);
+    }
+
+    // Called every loop iteration, only touches the disk every FLUSH_INTERVAL
+    pub fn flush_if_due(&mut self, max_bytes: u64) -> Result<()> {
+        if self.pending.is_empty() || self.last_flush.elapsed() < FLUSH_INTERVAL {
+            return Ok(());
+        }
+        self.flush(max_bytes)
+    }
+
+    // Lines that could not be written stay pending for the next flush
+    pub fn flush(&mut self, max_bytes: u64) -> Result<()> {
+        self.last_flush = Instant::now();
+        let mut first_error = None;
+        for (path, lines) in std::mem::take(&mut self.pending) {
+            if let Err(e) = write_lines(&path, &lines, max_bytes) {
+                self.pending.insert(path, lines);
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+fn write_lines(path: &Path, lines: &str, max_bytes: u64) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Full logs move aside to <channel>.log.1, replacing the previous one
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if max_bytes > 0 && size > 0 && size + lines.len() as u64 > max_bytes {
+        fs::rename(path, path.with_extension("log.1"))?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
@@ -1,79 +1,167 @@
 use color_eyre::eyre::Result;
 use ratatui::{DefaultTerminal, crossterm::{event::{self, Event}}};
-use crossterm::event::{EnableMouseCapture, DisableMouseCapture};
+use crossterm::event::{EnableMouseCapture, DisableMouseCapture, EnableBracketedPaste, DisableBracketedPaste};
 use crossterm::execute;
+use std::io::Write;
 use tokio::sync::mpsc;
 use tokio::time::Duration;
 mod app;
-use app::{App, ClientInfo, ChannelInfo, ChannelContext};
-use app::ServerTreeItem;
+use app::{App, ChannelInfo, ChannelContext};
 mod irc;
 use irc::*;
 mod ui;
-use ui::render;
+use ui::{render, message_viewport_height};
 mod servers;
+mod config;
+mod theme;
+mod logging;
+mod clipboard;
+mod help;
 mod click_state;
 use click_state::ClickState;
 mod mouse_handlers;
 use mouse_handlers::handle_mouse_event;
 mod keyboard_handlers;
 use keyboard_handlers::*;
+mod demo;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
 
+    let Some(startup) = parse_args() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
     let (irc_tx, irc_rx) = mpsc::unbounded_channel::<IrcCommand>();  // UI -> IRC
     let (ui_tx, mut ui_rx) = mpsc::unbounded_channel::<UiEvent>(); // IRC -> UI
     
-    // Start the IRC client
-    tokio::spawn(run_irc(ui_tx.clone(), irc_rx));
+    // Start the IRC client, or the scripted stand-in for --demo
+    let demo = matches!(startup, Startup::Demo);
+    if demo {
+        tokio::spawn(demo::run_demo(ui_tx.clone(), irc_rx));
+    } else {
+        tokio::spawn(run_irc(ui_tx.clone(), irc_rx));
+    }
     
     let mut app = App::new();
     app.push_initial_messages();
+    if demo {
+        app.demo = true;
+        app.servers.push(demo::demo_server());
+        app.rebuild_server_tree();
+    }
     
-    let initial_nick = get_user_nick().unwrap_or("guest".to_string());
+    let initial_nick = get_user_nick().unwrap_or(DEFAULT_NICK.to_string());
     app.current_nick = initial_nick;
-    execute!(std::io::stdout(), EnableMouseCapture)?;
+
+    let startup_server = match startup {
+        Startup::Connect(server) => Some(server),
+        Startup::Autoconnect => {
+            let server = app.autoconnect_server();
+            if server.is_none() {
+                app.push_system_to_current("No server has autoconnect = true in servers.toml".to_string());
+            }
+            server
+        }
+        Startup::Plain | Startup::Demo => None,
+    };
+    if let Some(server) = startup_server {
+        irc_tx.send(IrcCommand::Connect(server.clone())).ok();
+        app.push_system_to_current(format!("Connecting to {}...", server));
+    }
+
+    execute!(std::io::stdout(), EnableBracketedPaste)?;
+    if app.config.ui.mouse {
+        execute!(std::io::stdout(), EnableMouseCapture)?;
+    }
     let terminal = ratatui::init();
     let result = run(terminal, &mut app, irc_tx, &mut ui_rx).await;
-    execute!(std::io::stdout(), DisableMouseCapture)?;
+    app.flush_logs(true);
+    execute!(std::io::stdout(), DisableMouseCapture, DisableBracketedPaste)?;
     ratatui::restore();
     result
 }
 
+const USAGE: &str = "Usage: duckIRC [--connect <server|host:port>] [--autoconnect] [--demo]
+
+  --connect <server>  Connect to a saved server (or host:port) on launch
+  --autoconnect       Connect to the server marked autoconnect = true in servers.toml
+  --demo              Try the interface on a scripted fake network, nothing is sent anywhere";
+
+enum Startup {
+    Plain,
+    Connect(String),
+    Autoconnect,
+    Demo,
+}
+
+// None for --help or anything we don't understand, the caller prints USAGE
+fn parse_args() -> Option<Startup> {
+    let mut startup = Startup::Plain;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--connect" | "-c" => startup = Startup::Connect(args.next()?),
+            "--autoconnect" | "-a" => startup = Startup::Autoconnect,
+            "--demo" => startup = Startup::Demo,
+            _ => return None,
+        }
+    }
+    Some(startup)
+}
+
 async fn run(
     mut terminal: DefaultTerminal, 
     app: &mut App,
     irc_tx: mpsc::UnboundedSender<IrcCommand>,
     ui_rx: &mut mpsc::UnboundedReceiver<UiEvent>,
 ) -> Result<()> {
-    let mut click_state = ClickState::new();
+    let mut click_state = ClickState::new(Duration::from_millis(app.config.ui.double_click_ms));
+    let mut mouse_captured = app.config.ui.mouse;
     loop {
         if app.should_quit {
             break;
         }
+
+        // :set mouse and reload_config only change the setting, capture follows it here
+        if app.config.ui.mouse != mouse_captured {
+            mouse_captured = app.config.ui.mouse;
+            if mouse_captured {
+                execute!(std::io::stdout(), EnableMouseCapture)?;
+            } else {
+                execute!(std::io::stdout(), DisableMouseCapture)?;
+            }
+        }
+
+        app.check_auto_away(&irc_tx);
+        app.flush_logs(false);
         
         // Check for IRC messages (non-blocking)
         while let Ok(event) = ui_rx.try_recv() {
             match event {
-                UiEvent::Connected { nick , server_name} => {
+                UiEvent::Connecting { server_name } => {
+                    // Commands are accepted from here on, the IRC task holds joins until registered
                     app.is_connected = true;
+                    app.connection_state = ConnectionState::Connecting;
+                    app.identified = false;
+                    app.enabled_caps.clear();
+                    app.isupport = ISupport::default();
+                    app.auto_away = false;
+                    app.last_server = Some(server_name.clone());
     
                     // Ensure we have a status channel for this server
-                    app.current_channel = Some(ChannelContext {
+                    app.set_current_channel(Some(ChannelContext {
                         server_name: server_name.clone(),
                         channel_name: "status".to_string(),
-                    });
+                    }));
                     
                     // Initialize messages for status channel
                     app.channel_messages
                         .entry((server_name.clone(), "status".to_string()))
                         .or_default();
                     
-                    app.push_system_to_current(format!("✔ Connected as {}", nick));
-                    app.push_system_to_current("':join #channel' to join a channel".to_string());
-                    
                     // Update server connection status
                     for server in &mut app.servers {
                         if server.name == server_name {
@@ -82,8 +170,24 @@ async fn run(
                         }
                     }
                 }
+                UiEvent::Registering { server_name } => {
+                    app.connection_state = ConnectionState::Registering;
+                    app.push_system_to(&server_name, "status", "Registering...".to_string());
+                }
+                UiEvent::Connected { nick , server_name} => {
+                    app.connection_state = ConnectionState::Connected;
+                    app.current_nick = nick.clone();
+                    app.push_system_to(&server_name, "status", format!("✔ Connected as {}", nick));
+                    app.push_system_to(&server_name, "status", "':join #channel' to join a channel".to_string());
+                }
                 UiEvent::Disconnected { server_name } => {
                     app.is_connected = false;
+                    app.connection_state = ConnectionState::Disconnected;
+                    app.identified = false;
+                    // Disconnect is also sent before every connect, only say so when something was connected
+                    if app.servers.iter().any(|s| s.name == server_name && s.is_connected) {
+                        app.push_system_to(&server_name, "status", format!("✖ Disconnected from {}", server_name));
+                    }
                     for server in &mut app.servers {
                         if server.name == server_name {
                             server.is_connected = false;
@@ -94,15 +198,92 @@ async fn run(
                 UiEvent::Message(msg) => {
                     // Parse nick from message if you use <nick> format
                     if let Some((nick, text)) = msg.strip_prefix('<').and_then(|s| s.split_once('>')) {
+                        if app.is_ignored(nick) {
+                            continue;
+                        }
                         app.push_user_msg_to_current(nick, text);
                     } else {
                         app.push_system_to_current(msg); // fallback for system messages
                     }
                 }
+                UiEvent::Status { server_name, text } => {
+                    app.push_system_to(&server_name, "status", text);
+                }
+                UiEvent::Presence { server_name, channel, kind, text, time } => {
+                    app.push_presence(&server_name, channel.as_deref(), kind, text, time);
+                }
+                UiEvent::PrivMsg { server_name, channel, nick, text, time, tags } => {
+                    if app.is_ignored(&nick) || app.is_self_echo(&channel, &nick, &text) {
+                        continue;
+                    }
+                    let focused = app.is_current_channel(&server_name, &channel);
+                    app.push_user_msg_to(&server_name, &channel, &nick, &text, time, tags);
+                    let own = nick.eq_ignore_ascii_case(&app.current_nick);
+                    if !focused && !own {
+                        app.mark_unread(&server_name, &channel);
+                    }
+                    if !focused && !own && app.is_highlight(&text) {
+                        app.mark_highlight(&server_name, &channel);
+                        if app.config.ui.bell {
+                            // terminal bell
+                            let mut out = std::io::stdout();
+                            out.write_all(b"\x07")?;
+                            out.flush()?;
+                        }
+                    }
+                }
+                UiEvent::Identified { server_name, identified } => {
+                    app.identified = identified;
+                    if identified {
+                        app.push_system_to_current(format!("✔ Identified with services on {}", server_name));
+                    }
+                }
+                UiEvent::CapsChanged { caps } => {
+                    app.enabled_caps = caps;
+                }
+                UiEvent::ServerRenamed { old, new } => {
+                    app.rename_server(&old, &new);
+                }
+                UiEvent::ISupport(isupport) => {
+                    app.isupport = isupport;
+                }
+                UiEvent::Topic { server_name, channel, topic, set_by, set_at } => {
+                    app.update_topic(&server_name, &channel, topic, set_by, set_at);
+                }
+                UiEvent::Notify { server_name, nick, online } => {
+                    if online {
+                        app.push_system_to_current(format!("● {} is online on {}", nick, server_name));
+                    } else {
+                        app.push_system_to_current(format!("○ {} went offline on {}", nick, server_name));
+                    }
+                }
+                UiEvent::ChannelMode { server_name, channel, text } => {
+                    app.push_system_to(&server_name, &channel, text);
+                }
+                UiEvent::Redact { server_name, channel, msgid } => {
+                    app.redact_message(&server_name, &channel, &msgid);
+                }
+                UiEvent::Joined { server_name, channel, count } => {
+                    app.show_join_summary(&server_name, &channel, count);
+                }
+                UiEvent::NickChange { server_name, old, new, channels, is_self } => {
+                    if is_self {
+                        app.current_nick = new.clone();
+                        app.push_system_to(&server_name, "status", format!("You are now known as {}", new));
+                    }
+                    for channel in &channels {
+                        app.push_system_to(&server_name, channel, format!("{} is now {}", old, new));
+                    }
+                }
+                UiEvent::ChannelList { entries, done } => {
+                    app.extend_channel_list(entries, done);
+                }
                 UiEvent::Error(err) => {
                     app.push_system_to_current(format!("✖ IRC error: {}", err));
+                    app.flash_error();
                     if err.contains("connection") || err.contains("connect") {
                         app.is_connected = false;
+                        app.connection_state = ConnectionState::Disconnected;
                     }
                 }
                 UiEvent::ChannelUpdate {
@@ -114,6 +295,7 @@ async fn run(
                     is_joined,
                     is_dm,
                 } => {
+                    let key = if is_joined { app.take_join_key(&server_name, &channel_name) } else { None };
                     for server in &mut app.servers {
                         if server.name != server_name {
                             continue;
@@ -122,12 +304,18 @@ async fn run(
                         // Try to find existing channel
                         let mut found = false;
 
+                        // Same case rule as the rest of the tree, "#Rust" and "#rust" are one channel
                         for channel in &mut server.channels {
-                            if channel.name == channel_name {
-                                channel.topic = topic.clone();
+                            if channel.name.eq_ignore_ascii_case(&channel_name) {
+                                if topic.is_some() {
+                                    channel.topic = topic.clone();
+                                }
                                 channel.client_count = Some(client_count);
                                 channel.is_joined = is_joined;
                                 channel.is_dm = is_dm;
+                                if key.is_some() {
+                                    channel.key = key.clone();
+                                }
                                 found = true;
                                 break;
                             }
@@ -139,18 +327,15 @@ async fn run(
                                 topic: topic.clone(),
                                 client_count: Some(client_count),
                                 is_joined,
-                                is_dm
+                                is_dm,
+                                key: key.clone(),
+                                ..Default::default()
                             });
                         }
                     }
 
                     if let Some(current) = &app.current_channel && (current.server_name == server_name && current.channel_name == channel_name) {
-                        app.clients = clients
-                            .into_iter()
-                            .map(|nick| ClientInfo {
-                                name: nick,
-                            })
-                            .collect();
+                        app.set_clients(clients);
                     }
 
                     app.rebuild_server_tree();
@@ -164,11 +349,19 @@ async fn run(
         if event::poll(Duration::from_millis(50))? {
             match event::read()? {
                 Event::Key(key) => {
+                    app.note_activity(&irc_tx);
                     handle_keyboard_event(key, app, &irc_tx);
                 }
-                Event::Mouse(mouse) => {
+                Event::Mouse(mouse) if app.config.ui.mouse => {
                     handle_mouse_event(app, mouse, &mut click_state, &irc_tx, &terminal);
                 }
+                Event::Paste(text) => {
+                    app.insert_paste(&text);
+                }
+                Event::Resize(_, height) => {
+                    app.resize_viewport(message_viewport_height(height, app.input_rows(), app.config.ui.tab_bar));
+                    terminal.draw(|f| {render(f, app);})?;
+                }
                 _ => {}
             }
         }
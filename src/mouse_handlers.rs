@@ -1,12 +1,11 @@
 use crate::app::{App, VimMode};
 use crate::click_state::ClickState;
 use crate::irc::IrcCommand;
-use crate::ChannelContext;
-use crate::ServerTreeItem;
 use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
-use std::iter::once;
+use std::process::{Command, Stdio};
 use tokio::sync::mpsc;
 use ratatui::DefaultTerminal;
+use ratatui::layout::Position;
 
 pub fn handle_mouse_event(
     app: &mut App,
@@ -15,14 +14,39 @@ pub fn handle_mouse_event(
     irc_tx: &mpsc::UnboundedSender<IrcCommand>,
     terminal: &DefaultTerminal
 ) {
+    app.end_replace_outside_insert();
     let x = mouse.column;
     let y = mouse.row;
-    let terminal_heigh = terminal.size().unwrap().height;
-    let terminal_width = terminal.size().unwrap().width;
+    // Everything above the input box moves up as multi-line input makes it taller,
+    // so measure from its top row as if it were one line high
+    let Ok(size) = terminal.size() else { return };
+    let terminal_heigh = size.height.saturating_sub((app.input_rows() as u16).saturating_sub(1));
+    let terminal_width = size.width;
     match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if app.vim_mode == VimMode::Command => {
+            handle_command_click(app, x, y);
+        }
         MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(ctx) = app.tab_at(x, y) {
+                app.switch_to_channel(ctx, irc_tx);
+                return;
+            }
+            if let Some(url) = app.url_at(x, y).map(str::to_string) {
+                open_url(app, &url);
+                return;
+            }
             handle_left_click(app, x, y, click_state, irc_tx, terminal_heigh, terminal_width);
+            app.start_mouse_selection(x, y);
         },
+        MouseEventKind::Down(MouseButton::Right) => {
+            handle_right_click(app, x, y, terminal_heigh, terminal_width);
+        }
+        MouseEventKind::Drag(MouseButton::Left) => {
+            app.update_mouse_selection(x, y);
+        }
+        MouseEventKind::Up(MouseButton::Left) => {
+            app.finish_mouse_selection();
+        }
         MouseEventKind::ScrollUp => {
             handle_scroll_up(app);
         }
@@ -33,6 +57,28 @@ pub fn handle_mouse_event(
     }
 }
 
+// --- url opener ---
+fn open_url(app: &mut App, url: &str) {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+
+    // The URL is passed as a single argument, never through a shell
+    let result = Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    match result {
+        Ok(mut child) => {
+            // Reap the opener in the background so it doesn't linger as a zombie
+            std::thread::spawn(move || child.wait());
+            app.push_system_to_current(format!("Opening {}", url));
+        }
+        Err(e) => app.push_system_to_current(format!("✖ Failed to open {}: {}", url, e)),
+    }
+}
+
 // --- left click handler ---
 fn handle_left_click(
     app: &mut App,
@@ -71,16 +117,7 @@ fn handle_server_click(
     click_state: &mut ClickState,
     terminal_height: u16,
 ) {
-    let tree_width = app
-    .servers
-    .iter()
-    .flat_map(|s| {
-        once(s.name.len())
-            .chain(s.channels.iter().map(|c| c.name.len()))
-    })
-    .max()
-    .unwrap_or(0) as u16
-    + 10;
+    let tree_width = app.tree_width();
 
     let input_area_start_y = terminal_height.saturating_sub(4);
 
@@ -100,69 +137,8 @@ fn handle_server_click(
         app.server_tree_index = tree_item_index;
         
         // If double-click, also execute the Enter action
-        if is_double && let Some(item) = app.server_tree.get(tree_item_index) {
-            match item {
-                ServerTreeItem::Server { server_idx } => {
-                    let server_idx_copy = *server_idx;
-                    let server = &app.servers[server_idx_copy];
-                    let server_name = server.name.clone();
-
-                    if app.is_server_connected(server_idx_copy) {
-                        irc_tx.send(IrcCommand::Disconnect).ok();
-                        app.push_system_to_current(format!("Disconnecting from {}...", server_name));
-                        
-                        app.current_channel = None;
-                        app.channel.clear();
-                    } else {
-                        // Disconnect any currently connected server
-                        irc_tx.send(IrcCommand::Disconnect).ok();
-
-                        // Connect to this server
-                        irc_tx.send(IrcCommand::Connect(server_name.clone())).ok();
-                        app.push_system_to_current(format!("Connecting to {}...", server_name));
-                        
-                        app.current_channel = Some(ChannelContext {
-                            server_name: server_name.clone(),
-                            channel_name: "status".to_string(),
-                        });
-
-                        app.channel_messages
-                            .entry((server_name.clone(), "status".to_string()))
-                            .or_default();
-                    }
-
-                    app.toggle_server_expansion(server_idx_copy);
-                }
-                ServerTreeItem::Channel { server_idx, channel_idx } => {
-                    let server = &app.servers[*server_idx];
-                    let channel = &server.channels[*channel_idx];
-                    let channel_name = channel.name.clone();
-
-                    // Auto-join the channel if connected to server
-                    if app.is_server_connected(*server_idx) {
-                        irc_tx.send(IrcCommand::Join(channel_name.clone())).ok();
-                        
-                        app.current_channel = Some(ChannelContext {
-                            server_name: server.name.clone(),
-                            channel_name: channel_name.clone(),
-                        });
-
-                        irc_tx.send(IrcCommand::SetCurrentChannel(channel_name.clone())).ok();
-                        
-                        // Initialize messages for this channel if needed
-                        app.channel_messages
-                            .entry((server.name.clone(), channel_name.clone()))
-                            .or_default();
-                        
-                        app.channel = channel_name.clone();
-                    } else {
-                        app.push_system_to_current(format!(
-                            "Not connected to server {}. Connect first.",
-                            server.name
-                        ));
-                    }
-                }
-            }
+        if is_double {
+            app.activate_server_tree_item(tree_item_index, irc_tx);
         }
     }
     } else if x >= message_area_start_x && y >= message_area_start_y && y < input_area_start_y {
@@ -198,7 +174,7 @@ fn handle_clients_click(
     click_state: &mut ClickState,
     irc_tx: &mpsc::UnboundedSender<IrcCommand>,
 ) {
-    let message_area_x_end = terminal_width.saturating_sub(16);
+    let message_area_x_end = terminal_width.saturating_sub(app.clients_width() + 1);
     let message_area_y_end = terminal_height.saturating_sub(4);
     let is_double = click_state.is_double_click(x, y);
     
@@ -220,6 +196,32 @@ fn handle_clients_click(
     }
 }
 
+// Inside the popup moves the cursor, outside dismisses it like Esc
+fn handle_command_click(app: &mut App, x: u16, y: u16) {
+    let area = app.cmd_popup_area;
+    if area.contains(Position::new(x, y)) {
+        // the text starts after the border and the ':'
+        app.set_cmd_cursor(x.saturating_sub(area.x + 2) as usize);
+    } else {
+        app.clear_cmd();
+        app.return_to_prev_mode();
+    }
+}
+
+// Right-click on a nick copies it
+fn handle_right_click(app: &mut App, x: u16, y: u16, terminal_height: u16, terminal_width: u16) {
+    let clients_x_start = terminal_width.saturating_sub(app.clients_width() + 1);
+    let clients_y_end = terminal_height.saturating_sub(4);
+    if app.vim_mode != VimMode::Clients || x <= clients_x_start || y > clients_y_end {
+        return;
+    }
+    app.move_client_to_index(y.saturating_sub(1) as usize);
+    app.yank_client();
+    if let Some(client) = app.get_selected_client() {
+        app.push_system_to_current(format!("Copied {}", client.name));
+    }
+}
+
 fn handle_messages_click(
     app: &mut App,
     y: u16,
@@ -243,17 +245,8 @@ fn handle_vimless_click(
     terminal_width: u16,
     irc_tx: &mpsc::UnboundedSender<IrcCommand>,
 ) {
-    let server_tree_width = app
-        .servers
-        .iter()
-        .flat_map(|s| {
-            once(s.name.len())
-                .chain(s.channels.iter().map(|c| c.name.len()))
-        })
-        .max()
-        .unwrap_or(0) as u16
-        + 10;
-    let message_area_end_x = terminal_width.saturating_sub(16);
+    let server_tree_width = app.tree_width();
+    let message_area_end_x = terminal_width.saturating_sub(app.clients_width() + 1);
     let message_area_start_y = 1;
     let input_area_start_y = terminal_height.saturating_sub(4);
     match (x, y) {
@@ -267,70 +260,7 @@ fn handle_vimless_click(
         }
         (x, y) if x <= server_tree_width && x < input_area_start_y => {
             let tree_item_index = (y as usize).saturating_sub(1);
-            if let Some(item) = app.server_tree.get(tree_item_index) {
-                match item {
-                    ServerTreeItem::Server { server_idx } => {
-                        let server_idx_copy = *server_idx;
-                        let server = &app.servers[server_idx_copy];
-                        let server_name = server.name.clone();
-
-                        if app.is_server_connected(server_idx_copy) {
-                            irc_tx.send(IrcCommand::Disconnect).ok();
-                            app.push_system_to_current(format!("Disconnecting from {}...", server_name));
-                            
-                            app.current_channel = None;
-                            app.channel.clear();
-                        } else {
-                            // Disconnect any currently connected server
-                            irc_tx.send(IrcCommand::Disconnect).ok();
-
-                            // Connect to this server
-                            irc_tx.send(IrcCommand::Connect(server_name.clone())).ok();
-                            app.push_system_to_current(format!("Connecting to {}...", server_name));
-                            
-                            app.current_channel = Some(ChannelContext {
-                                server_name: server_name.clone(),
-                                channel_name: "status".to_string(),
-                            });
-
-                            app.channel_messages
-                                .entry((server_name.clone(), "status".to_string()))
-                                .or_default();
-                        }
-
-                        app.toggle_server_expansion(server_idx_copy);
-                    }
-                    ServerTreeItem::Channel { server_idx, channel_idx } => {
-                        let server = &app.servers[*server_idx];
-                        let channel = &server.channels[*channel_idx];
-                        let channel_name = channel.name.clone();
-
-                        // Auto-join the channel if connected to server
-                        if app.is_server_connected(*server_idx) {
-                            irc_tx.send(IrcCommand::Join(channel_name.clone())).ok();
-                            
-                            app.current_channel = Some(ChannelContext {
-                                server_name: server.name.clone(),
-                                channel_name: channel_name.clone(),
-                            });
-
-                            irc_tx.send(IrcCommand::SetCurrentChannel(channel_name.clone())).ok();
-                            
-                            // Initialize messages for this channel if needed
-                            app.channel_messages
-                                .entry((server.name.clone(), channel_name.clone()))
-                                .or_default();
-                            
-                            app.channel = channel_name.clone();
-                        } else {
-                            app.push_system_to_current(format!(
-                                "Not connected to server {}. Connect first.",
-                                server.name
-                            ));
-                        }
-                    }
-                }
-            }
+            app.activate_server_tree_item(tree_item_index, irc_tx);
         }
         _ => {}
     }
@@ -339,7 +269,8 @@ fn handle_vimless_click(
 // --- scroll up handler ---
 fn handle_scroll_up(app: &mut App) {
     match app.vim_mode {
-        VimMode::Messages => {
+        // reading while typing shouldn't need a mode switch
+        VimMode::Messages | VimMode::Normal | VimMode::Insert => {
             app.move_msg_up();
         }
         VimMode::Clients => {
@@ -348,6 +279,12 @@ fn handle_scroll_up(app: &mut App) {
         VimMode::Server => {
             app.move_server_selection_up();
         }
+        VimMode::Help => {
+            app.scroll_help_up();
+        }
+        VimMode::ChannelList => {
+            app.move_channel_list_up(1);
+        }
         _ => {}
     }
 }
@@ -355,7 +292,7 @@ fn handle_scroll_up(app: &mut App) {
 // --- scroll down handler ---
 fn handle_scroll_down(app: &mut App) {
     match app.vim_mode {
-        VimMode::Messages => {
+        VimMode::Messages | VimMode::Normal | VimMode::Insert => {
             app.move_msg_down();
         }
         VimMode::Clients => {
@@ -364,6 +301,12 @@ fn handle_scroll_down(app: &mut App) {
         VimMode::Server => {
             app.move_server_selection_down();
         }
+        VimMode::Help => {
+            app.scroll_help_down();
+        }
+        VimMode::ChannelList => {
+            app.move_channel_list_down(1);
+        }
         _ => {}
     }
 }
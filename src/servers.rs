@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -10,6 +10,15 @@ pub struct Server {
     pub port: u16,
     #[serde(default = "default_use_tls")]
     pub use_tls: bool,
+    // shown in the tree before connecting, ready to be joined
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<String>,
+    // sent as PASS before registering, bouncers take things like "user/network:pass" so it's used verbatim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    // connected to on launch with --autoconnect
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub autoconnect: bool,
 }
 
 fn default_use_tls() -> bool {
@@ -56,12 +65,18 @@ impl ServerConfig {
                     address: "irc.libera.chat".to_string(),
                     port: 6697,
                     use_tls: true,
+                    channels: Vec::new(),
+                    password: None,
+                    autoconnect: false,
                 },
                 Server {
                     name: "OFTC".to_string(),
                     address: "irc.oftc.net".to_string(),
                     port: 6697,
                     use_tls: true,
+                    channels: Vec::new(),
+                    password: None,
+                    autoconnect: false,
                 },
             ],
         }
@@ -77,6 +92,9 @@ impl ServerConfig {
             address,
             port,
             use_tls,
+            channels: Vec::new(),
+            password: None,
+            autoconnect: false,
         });
         true
     }
@@ -90,6 +108,35 @@ impl ServerConfig {
         }
     }
     
+    // Change one field of a saved server in place so it keeps its spot in the list
+    pub fn update_server(&mut self, name: &str, field: &str, value: &str) -> Result<()> {
+        // Renaming a server to its own name is a no-op, not a clash
+        if field == "name" && value != name && self.servers.iter().any(|s| s.name == value) {
+            bail!("Server with name '{}' already exists", value);
+        }
+        let server = self.servers
+            .iter_mut()
+            .find(|s| s.name == name)
+            .ok_or_else(|| anyhow!("Server not found: {}", name))?;
+
+        match field {
+            "name" => server.name = value.to_string(),
+            "address" => server.address = value.to_string(),
+            "password" => server.password = Some(value.to_string()).filter(|p| !p.is_empty()),
+            "port" => {
+                server.port = value.parse().map_err(|_| anyhow!("Invalid port number: {}", value))?;
+            }
+            "tls" | "use_tls" => {
+                server.use_tls = value.parse().map_err(|_| anyhow!("tls must be true or false, got '{}'", value))?;
+            }
+            "autoconnect" => {
+                server.autoconnect = value.parse().map_err(|_| anyhow!("autoconnect must be true or false, got '{}'", value))?;
+            }
+            _ => bail!("Unknown field '{}', expected name, address, port, tls, password or autoconnect", field),
+        }
+        Ok(())
+    }
+
     pub fn get_server(&self, name: &str) -> Option<&Server> {
         self.servers.iter().find(|s| s.name == name)
     }
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs;
+use std::path::Path;
+
+// Colors are written as names ("lightblue"), indexes ("236") or hex ("#2d3f76")
+mod color {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&color.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(|_| serde::de::Error::custom(format!("invalid color '{}'", s)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeColors {
+    #[serde(with = "color")]
+    pub normal: Color,
+    #[serde(with = "color")]
+    pub insert: Color,
+    #[serde(with = "color")]
+    pub visual: Color,
+    #[serde(with = "color")]
+    pub command: Color,
+    #[serde(with = "color")]
+    pub server: Color,
+    #[serde(with = "color")]
+    pub messages: Color,
+    #[serde(with = "color")]
+    pub clients: Color,
+    #[serde(with = "color")]
+    pub vimless: Color,
+    #[serde(with = "color")]
+    pub popup: Color,
+    // text on top of the mode bar
+    #[serde(with = "color")]
+    pub text: Color,
+}
+
+impl Default for ModeColors {
+    fn default() -> Self {
+        Self {
+            normal: Color::Blue,
+            insert: Color::LightGreen,
+            visual: Color::LightMagenta,
+            command: Color::Yellow,
+            server: Color::Cyan,
+            messages: Color::LightBlue,
+            clients: Color::LightCyan,
+            vimless: Color::Gray,
+            popup: Color::LightYellow,
+            text: Color::Black,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    // selected row in the server tree, clients and messages
+    #[serde(with = "color")]
+    pub highlight_bg: Color,
+    // visual mode and mouse selections
+    #[serde(with = "color")]
+    pub selection_bg: Color,
+    #[serde(with = "color")]
+    pub selection_fg: Color,
+    #[serde(with = "color")]
+    pub border: Color,
+    #[serde(with = "color")]
+    pub system_message: Color,
+    // timestamps, tree lines, folded join/part/quit
+    #[serde(with = "color")]
+    pub muted: Color,
+    #[serde(with = "color")]
+    pub mention: Color,
+    #[serde(with = "color")]
+    pub url: Color,
+    pub modes: ModeColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            highlight_bg: Color::Rgb(45, 63, 118),
+            selection_bg: Color::DarkGray,
+            selection_fg: Color::Black,
+            border: Color::Reset,
+            system_message: Color::Gray,
+            muted: Color::DarkGray,
+            mention: Color::LightRed,
+            url: Color::Cyan,
+            modes: ModeColors::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn load(path: &str) -> Result<Self> {
+        let path = Path::new(path);
+
+        // Create parent directory if it doesn't exist
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // If file doesn't exist, create with defaults
+        if !path.exists() {
+            let default_theme = Self::default();
+            default_theme.save(path.to_str().unwrap())?;
+            return Ok(default_theme);
+        }
+
+        let contents = fs::read_to_string(path)?;
+        let theme: Theme = toml::from_str(&contents)?;
+        Ok(theme)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let toml_string = toml::to_string_pretty(self)?;
+        fs::write(path, toml_string)?;
+        Ok(())
+    }
+}
@@ -5,18 +5,27 @@ use ratatui::{
     symbols::line,
     text::{Span, Line},
 };
-use crate::app::{App, VimMode};
+use crate::app::{App, ClientInfo, TabSpan, UrlSpan, VimMode};
+use chrono::{DateTime, Local};
 use crate::app::ServerTreeItem;
+use crate::help::HELP_SECTIONS;
+use crate::config::WrapMode;
+use crate::irc::ConnectionState;
 use crossterm::cursor::SetCursorStyle;
 use crossterm::execute;
+use std::fmt::Write;
 use std::io::stdout;
 
 pub fn render(frame: &mut Frame, app: &mut App) {
     // ── Snapshot immutable app state ────────────────────────────
+    let theme = app.theme.clone();
     let vim_mode = app.vim_mode.clone();
     let prev_mode = app.prev_mode.clone();
 
-    let channel_name = app.channel.clone();
+    let channel_name = match &app.current_channel {
+        Some(_) => app.channel.clone(),
+        None => "system".to_string(),
+    };
     let clients = app.clients.clone();
     let client_index = app.client_index;
 
@@ -26,7 +35,8 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     let mode_name = app.get_mode_name().to_string();
     let msg_chars: Vec<char> = app.get_msg_iter().collect();
     let selection = app.msg_selection_range();
-    let msg_cursor_pos = app.msg_cursor_position();
+    let (msg_cursor_row, msg_cursor_col) = app.msg_cursor_row_col();
+    let input_rows = app.input_rows();
 
     // ── Cursor style ─────────────────────────────────────────────
     let cursor_style = match vim_mode {
@@ -40,58 +50,59 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     let [border_area] =
         Layout::vertical([Constraint::Fill(1)]).areas(frame.area());
 
-    Block::bordered()
-        .border_type(BorderType::Rounded)
-        .render(border_area, frame.buffer_mut());
+    let flashing = app.is_flashing();
+    let mut outer = Block::bordered().border_type(BorderType::Rounded);
+    if flashing {
+        outer = outer.border_style(Style::default().fg(Color::Red));
+    }
+    outer.render(border_area, frame.buffer_mut());
 
     // ── Main vertical layout ─────────────────────────────────────
     let layout = Layout::vertical([
         Constraint::Min(1),
-        Constraint::Length(3),
+        Constraint::Length(2 + input_rows as u16),
     ])
     .split(frame.area());
 
     // ── Horizontal main split ────────────────────────────────────
-    let tree_width = app
-        .servers
-        .iter()
-        .flat_map(|s| {
-            std::iter::once(s.name.len())
-                .chain(s.channels.iter().map(|c| c.name.len()))
-        })
-        .max()
-        .unwrap_or(0) as u16
-        + 10;
+    let tree_width = app.tree_width();
 
-    let servers_tab = vim_mode ==  VimMode::Server 
+    let servers_tab = app.pin_servers
+        || vim_mode ==  VimMode::Server 
         || (vim_mode == VimMode::Command && prev_mode == Some(VimMode::Server))
         || vim_mode == VimMode::Vimless;
 
-    let clients_tab = vim_mode ==  VimMode::Clients 
+    let clients_tab = app.pin_clients
+        || vim_mode ==  VimMode::Clients 
         || (vim_mode == VimMode::Command && prev_mode == Some(VimMode::Clients))
         || vim_mode == VimMode::Vimless;
 
     let main_chunks = Layout::horizontal([
         Constraint::Length(if servers_tab { tree_width } else { 0 }),
         Constraint::Min(1),
-        Constraint::Length(if clients_tab { 15 } else { 0 }),
+        Constraint::Length(if clients_tab { app.clients_width() } else { 0 }),
     ])
     .split(layout[0]);
 
     // ── Servers tree ─────────────────────────────────────────────
     if servers_tab {
         let items = create_tree_view(app);
+        let servers_title = match &app.server_filter {
+            Some(filter) => format!("Servers /{}", filter),
+            None => "Servers".to_string(),
+        };
 
         let widget = List::new(items)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
                     .border_type(BorderType::Rounded)
-                    .title("Servers"),
+                    .title(servers_title),
             )
             .highlight_style(
                 Style::default()
-                    .bg(Color::Rgb(45, 63, 118))
+                    .bg(theme.highlight_bg)
                     .bold(),
             );
 
@@ -101,77 +112,255 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         );
     }
 
+    // ── Channel tabs ─────────────────────────────────────────────
+    let tab_bar = app.config.ui.tab_bar;
+    let msg_pane = if tab_bar {
+        let [tabs_area, msg_pane] = Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(main_chunks[1]);
+        render_channel_tabs(frame, app, tabs_area);
+        msg_pane
+    } else {
+        app.channel_tabs.clear();
+        main_chunks[1]
+    };
+
     // ── Messages  ───────────────────────────────────────────
     let mut message_lines = Vec::new();
-    let mut msg_index = 0usize;
-    let mut msg_scroll = 0usize;
+    let mut msg_row = 0usize;
+    let mut selected_age = None;
+    let timestamp_format = app.config.ui.timestamp_format.clone();
+    let system_prefix = app.config.ui.system_prefix.clone();
+    let nick_column = app.config.ui.nick_column;
+    let group_messages_secs = app.config.ui.group_messages_secs;
+    let wrap = app.config.ui.wrap;
+    let msg_hscroll = app.msg_hscroll as u16;
+    let line_selection = app.msg_line_selection().filter(|_| vim_mode == VimMode::Messages);
 
     if let Some(msgs) = app.get_current_messages_mut() {
-        let viewport_height =
-            main_chunks[1].height.saturating_sub(3) as usize;
+        let viewport_height = message_viewport_height(frame.area().height, input_rows, tab_bar);
 
-        msgs.viewport_height = viewport_height;
-        msg_index = msgs.msg_index;
-        msg_scroll = msgs.msg_scroll;
+        // The input box grew or shrank, keep the newest message in view
+        if msgs.viewport_height != viewport_height {
+            msgs.viewport_height = viewport_height;
+            if msgs.stick_to_bottom {
+                msgs.msg_scroll = msgs.bottom_scroll();
+            }
+        }
+        let msg_index = msgs.msg_index;
 
         let start = msgs.msg_scroll;
         let end = (start + viewport_height).min(msgs.messages.len());
 
-        message_lines = msgs.messages[start..end]
-            .iter()
-            .enumerate()
-            .map(|(i, msg)| {
-                let absolute = start + i;
-
-                let mut line = if let Some(nick) = &msg.nick {
-                    Line::from(vec![
-                        Span::styled(
-                            format!("<{}>", nick),
-                            Style::default()
-                                .fg(msg.color.unwrap_or(Color::White)),
-                        ),
-                        Span::raw(format!(" {}", msg.text)),
-                    ])
+        if vim_mode == VimMode::Messages && let Some(msg) = msgs.messages.get(msg_index) {
+            selected_age = Some(format_relative_time(msg.time, Local::now()));
+        }
+
+        let messages = &msgs.messages;
+        let last_read_index = msgs.last_read_index;
+        // Same sender again soon after, its nick is left blank. A divider row
+        // in between ("new messages" or a day separator) starts a new run
+        let continues_run = |absolute: usize| {
+            let msg = &messages[absolute];
+            group_messages_secs > 0 && absolute > 0 && msg.nick.is_some() && last_read_index != Some(absolute) && {
+                let prev = &messages[absolute - 1];
+                prev.nick == msg.nick
+                    && prev.time.date_naive() == msg.time.date_naive()
+                    && (msg.time - prev.time).num_seconds() <= group_messages_secs as i64
+            }
+        };
+        let message_line = |absolute: usize, grouped: bool| {
+            let msg = &messages[absolute];
+            let mut line = if let Some(nick) = &msg.nick {
+                let nick_shown = aligned_nick(nick, nick_column);
+                let mut spans = vec![
+                    Span::styled(
+                        if grouped { " ".repeat(nick_shown.chars().count()) } else { nick_shown },
+                        Style::default()
+                            .fg(msg.color.unwrap_or(Color::White)),
+                    ),
+                    Span::raw(" "),
+                ];
+                // "nick: ..." addressed to a member gets that member's color
+                match leading_mention(&msg.text, &clients) {
+                    Some((mentioned, rest)) => {
+                        spans.push(Span::styled(mentioned, Style::default().fg(color_for_user(mentioned))));
+                        spans.extend(text_spans(rest, theme.url));
+                    }
+                    None => spans.extend(text_spans(&msg.text, theme.url)),
+                }
+                Line::from(spans)
+            } else if msg.presence.len() > 1 {
+                // Folded join/part/quit run, spelled out while selected
+                let selected = vim_mode == VimMode::Messages && absolute == msg_index;
+                let text = if selected {
+                    let details: Vec<&str> = msg.presence.iter().map(|(_, t)| t.as_str()).collect();
+                    format!("▾ {}: {}", msg.text, details.join(", "))
                 } else {
-                    Line::from(Span::raw(&msg.text))
+                    format!("▸ {}", msg.text)
                 };
+                Line::from(Span::styled(text, Style::default().fg(theme.muted)))
+            } else if msg.banner {
+                Line::from(msg.text.clone())
+            } else {
+                let mut spans = Vec::new();
+                if nick_column > 0 {
+                    // "<>" around the nick take two more columns
+                    spans.push(Span::raw(format!("{:>1$} ", system_prefix, nick_column + 2)));
+                } else if !system_prefix.is_empty() {
+                    spans.push(Span::raw(format!("{} ", system_prefix)));
+                }
+                spans.extend(text_spans(&msg.text, theme.url));
+                Line::from(spans).style(Style::default().fg(theme.system_message))
+            };
 
-                if vim_mode == VimMode::Messages && absolute == msg_index {
-                    line.spans = line.spans.into_iter()
-                        .map(|s| Span::styled(
-                            s.content,
-                            s.style.bg(Color::Rgb(45, 63, 118)).bold(),
-                        ))
-                        .collect();
+            if msg.historical {
+                line = line.style(Style::default().fg(theme.muted));
+                for span in &mut line.spans {
+                    span.style = span.style.fg(theme.muted);
                 }
+            }
 
-                line
+            // write! instead of format! so a bad format string from config can't panic
+            let mut stamp = String::new();
+            if !timestamp_format.is_empty() && write!(stamp, "{} ", msg.time.format(&timestamp_format)).is_ok() {
+                line.spans.insert(0, Span::styled(stamp, Style::default().fg(theme.muted)));
+            }
+
+            if line_selection.is_some_and(|(first, last)| (first..=last).contains(&absolute)) {
+                let bold = absolute == msg_index;
+                line.spans = line.spans.into_iter()
+                    .map(|s| {
+                        let style = s.style.bg(theme.selection_bg).fg(theme.selection_fg);
+                        Span::styled(s.content, if bold { style.bold() } else { style })
+                    })
+                    .collect();
+            } else if vim_mode == VimMode::Messages && absolute == msg_index {
+                line.spans = line.spans.into_iter()
+                    .map(|s| Span::styled(
+                        s.content,
+                        s.style.bg(theme.highlight_bg).bold(),
+                    ))
+                    .collect();
+            }
+            line
+        };
+        // Message index of every row, None for day separators
+        let mut rows: Vec<(Option<usize>, Line)> = (start..end)
+            .flat_map(|absolute| {
+                let msg = &messages[absolute];
+                let mut rows = Vec::new();
+                if absolute > 0 && last_read_index == Some(absolute) {
+                    rows.push((None, Line::from(Span::styled("── new messages ──", Style::default().fg(theme.mention))).centered()));
+                }
+                if absolute > 0 && messages[absolute - 1].time.date_naive() != msg.time.date_naive() {
+                    let label = format!("── {} ──", msg.time.format(DAY_SEPARATOR_FORMAT));
+                    rows.push((None, Line::from(Span::styled(label, Style::default().fg(theme.muted))).centered()));
+                }
+                // The message being answered, quoted above the reply when it is still in the buffer
+                let parent = msg.reply_to.as_deref()
+                    .and_then(|id| messages.iter().find(|m| m.msgid.as_deref() == Some(id)));
+                if let Some(parent) = parent {
+                    let mut quote = format!("  ┌ <{}> {}", parent.nick.as_deref().unwrap_or("*"), parent.text);
+                    if quote.chars().count() > REPLY_QUOTE_WIDTH {
+                        quote = quote.chars().take(REPLY_QUOTE_WIDTH - 1).collect::<String>() + "…";
+                    }
+                    rows.push((None, Line::from(Span::styled(quote, Style::default().fg(theme.muted)))));
+                }
+                rows.push((Some(absolute), message_line(absolute, continues_run(absolute))));
+                rows
             })
             .collect();
+
+        // Separators take rows of their own, when following the bottom the oldest lines make room
+        if msgs.stick_to_bottom && rows.len() > viewport_height {
+            rows.drain(..rows.len() - viewport_height);
+        }
+        msg_row = rows.iter().position(|(index, _)| *index == Some(msg_index)).unwrap_or(0);
+        // Scrolled up they can push the selection below the pane, drop from the top until it shows
+        if msg_row >= viewport_height {
+            rows.drain(..msg_row + 1 - viewport_height);
+            msg_row = viewport_height.saturating_sub(1);
+            if let Some(first) = rows.iter().find_map(|(index, _)| *index) {
+                msgs.msg_scroll = first;
+            }
+        }
+        // The top row always names its sender, whatever was trimmed above it
+        if let Some((Some(first), line)) = rows.iter_mut().find(|(index, _)| index.is_some())
+            && continues_run(*first)
+        {
+            *line = message_line(*first, false);
+        }
+        message_lines = rows.into_iter().map(|(_, line)| line).collect();
     }
 
-    let messages_widget = Paragraph::new(message_lines)
+    let mut messages_widget = Paragraph::new(message_lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border))
                 .border_type(BorderType::Rounded)
-                .title(format!("{} messages", channel_name)),
-        )
-        .wrap(Wrap { trim: true });
+                .title(match &selected_age {
+                    Some(age) => format!("{} messages ({})", channel_name, age),
+                    None => format!("{} messages", channel_name),
+                }),
+        );
+    messages_widget = match wrap {
+        WrapMode::Trim => messages_widget.wrap(Wrap { trim: true }),
+        WrapMode::On => messages_widget.wrap(Wrap { trim: false }),
+        WrapMode::Off => messages_widget.scroll((0, msg_hscroll)),
+    };
+
+    frame.render_widget(messages_widget, msg_pane);
 
-    frame.render_widget(messages_widget, main_chunks[1]);
+    // Remember what the message pane shows so mouse selections can be copied
+    let msg_area = msg_pane.inner(Margin::new(1, 1));
+    let selection_cells = app.mouse_selection_cells();
+    let buf = frame.buffer_mut();
+    app.msg_area = msg_area;
+    app.msg_rows = msg_area
+        .rows()
+        .map(|row| {
+            row.columns()
+                .map(|pos| buf[pos].symbol().chars().next().unwrap_or(' '))
+                .collect()
+        })
+        .collect();
+
+    app.msg_urls = find_url_spans(&app.msg_rows, msg_area);
+
+    if let Some((sel_start, sel_end)) = selection_cells {
+        for y in sel_start.1..=sel_end.1 {
+            let from = if y == sel_start.1 { sel_start.0 } else { msg_area.left() };
+            let to = if y == sel_end.1 { sel_end.0 + 1 } else { msg_area.right() };
+            for x in from..to {
+                buf[(x, y)].set_bg(theme.selection_bg).set_fg(theme.selection_fg);
+            }
+        }
+    }
 
     // ── Clients panel ─────────────────────────────────────────────
     if clients_tab {
-        let items: Vec<ListItem> = clients
-            .iter()
+        let items: Vec<ListItem> = app
+            .visible_clients()
             .map(|c| {
-                ListItem::new(Span::styled(
-                    &c.name,
-                    Style::default()
-                        .fg(color_for_user(&c.name))
-                        .bold(),
-                ))
+                // by mode rather than symbol, networks disagree on what '&' or '!' mean
+                let prefix_color = match c.prefix.and_then(|p| app.isupport.prefix_mode(p)) {
+                    Some('q') | Some('a') | Some('Y') | Some('y') => Color::LightRed,
+                    Some('o') => Color::Green,
+                    Some('h') => Color::Cyan,
+                    _ => Color::Yellow,
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        c.prefix.map(String::from).unwrap_or_else(|| " ".to_string()),
+                        Style::default().fg(prefix_color).bold(),
+                    ),
+                    Span::styled(
+                        &c.name,
+                        Style::default()
+                            .fg(color_for_user(&c.name))
+                            .bold(),
+                    ),
+                ]))
             })
             .collect();
 
@@ -179,12 +368,16 @@ pub fn render(frame: &mut Frame, app: &mut App) {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
                     .border_type(BorderType::Rounded)
-                    .title("Clients"),
+                    .title(match &app.client_filter {
+                        Some(filter) => format!("Clients /{}", filter),
+                        None => "Clients".to_string(),
+                    }),
             )
             .highlight_style(
                 Style::default()
-                    .bg(Color::Rgb(45, 63, 118))
+                    .bg(theme.highlight_bg)
                     .bold(),
             );
 
@@ -195,11 +388,30 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     }
 
     // ── Input bar ────────────────────────────────────────────────
-    let input_block = Block::default()
+    let mut input_block = Block::default()
         .borders(Borders::ALL)
+        .border_style(Style::default().fg(if flashing { Color::Red } else { theme.border }))
         .border_type(BorderType::Rounded)
         .title("Input");
 
+    // Our nick on the right, with a check once services recognise it
+    if app.is_connected && !app.current_nick.is_empty() {
+        let mut nick_spans = vec![Span::styled(format!(" {}", app.current_nick), Style::default().bold())];
+        if app.identified {
+            nick_spans.push(Span::styled(" ✔", Style::default().fg(Color::Green)));
+        }
+        if app.connection_state != ConnectionState::Connected {
+            // Spins while we wait so a slow host doesn't look like a frozen UI
+            let frame_idx = (Local::now().timestamp_millis() / 100) as usize % SPINNER.len();
+            nick_spans.push(Span::styled(
+                format!(" {} ({}...)", SPINNER[frame_idx], app.connection_state.as_str().to_lowercase()),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        nick_spans.push(Span::raw(" "));
+        input_block = input_block.title(Line::from(nick_spans).right_aligned());
+    }
+
     frame.render_widget(input_block.clone(), layout[1]);
     let inner = input_block.inner(layout[1]);
 
@@ -210,34 +422,58 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     .split(inner);
 
     let bg = match vim_mode {
-        VimMode::Normal => Color::Blue,
-        VimMode::Insert => Color::LightGreen,
-        VimMode::Visual => Color::LightMagenta,
-        VimMode::Command => Color::Yellow,
-        VimMode::Server => Color::Cyan,
-        VimMode::Messages => Color::LightBlue,
-        VimMode::Clients => Color::LightCyan,
-        VimMode::Vimless => Color::Gray,
+        VimMode::Normal => theme.modes.normal,
+        VimMode::Insert => theme.modes.insert,
+        VimMode::Visual => theme.modes.visual,
+        VimMode::Command => theme.modes.command,
+        VimMode::Server => theme.modes.server,
+        VimMode::Messages => theme.modes.messages,
+        VimMode::Clients => theme.modes.clients,
+        VimMode::Vimless => theme.modes.vimless,
+        VimMode::Help => theme.modes.popup,
+        VimMode::ChannelList => theme.modes.popup,
     };
 
     frame.render_widget(
         Paragraph::new(mode_name)
             .alignment(Alignment::Center)
-            .style(Style::default().bg(bg).fg(Color::Black).bold()),
+            .style(Style::default().bg(bg).fg(theme.modes.text).bold()),
         input_chunks[0],
     );
 
     let mut spans = vec![Span::raw(" ")];
+
+    // Preview of how the message will show up, in the colour others see our nick in
+    let mut nick_width = 0;
+    if matches!(vim_mode, VimMode::Insert | VimMode::Vimless) && !app.current_nick.is_empty() {
+        let preview = format!("<{}> ", app.current_nick);
+        nick_width = preview.chars().count() as u16;
+        spans.push(Span::styled(
+            preview,
+            Style::default().fg(color_for_user(&app.current_nick)).dim(),
+        ));
+    }
+
+    // One row per line of the input, later lines indented under the first
+    let mut input_lines = Vec::new();
     for (i, c) in msg_chars.iter().enumerate() {
+        if *c == '\n' {
+            input_lines.push(Line::from(std::mem::take(&mut spans)));
+            spans.push(Span::raw(" ".repeat(1 + nick_width as usize)));
+            continue;
+        }
         let mut style = Style::default().bold();
         if let Some((s, e)) = selection && i >= s && i < e {
-            style = style.bg(Color::DarkGray).fg(Color::Black);
+            style = style.bg(theme.selection_bg).fg(theme.selection_fg);
         }
         spans.push(Span::styled(c.to_string(), style));
     }
+    input_lines.push(Line::from(spans));
 
+    // Past MAX_INPUT_ROWS the box scrolls to keep the cursor's row visible
+    let input_scroll = msg_cursor_row.saturating_sub(input_rows - 1);
     frame.render_widget(
-        Paragraph::new(Line::from(spans)),
+        Paragraph::new(input_lines).scroll((input_scroll as u16, 0)),
         input_chunks[1],
     );
 
@@ -248,29 +484,77 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     let buf = frame.buffer_mut();
 
     buf[(sep_x, top)].set_symbol(line::HORIZONTAL_DOWN);
-    buf[(sep_x, top + 1)].set_symbol(line::VERTICAL);
+    for y in top + 1..bottom {
+        buf[(sep_x, y)].set_symbol(line::VERTICAL);
+    }
     buf[(sep_x, bottom)].set_symbol(line::HORIZONTAL_UP);
 
+    // ── Nick completion popup ────────────────────────────────────
+    if let Some(popup) = &app.nick_popup {
+        let rows = popup.matches.len().min(NICK_POPUP_ROWS);
+        let first = popup.index.saturating_sub(rows - 1);
+        let width = popup.matches.iter().map(|m| m.chars().count()).max().unwrap_or(0) as u16 + 4;
+        let height = rows as u16 + 2;
+
+        // Hangs above the input, its left edge under the start of the word being completed
+        let word_len = app.msg_cursor.saturating_sub(popup.start) as u16;
+        let anchor = (input_chunks[1].x + nick_width + msg_cursor_col as u16).saturating_sub(word_len + 1);
+        let area = Rect {
+            x: anchor.min(frame.area().width.saturating_sub(width)),
+            y: layout[1].y.saturating_sub(height),
+            width: width.min(frame.area().width),
+            height: height.min(layout[1].y),
+        };
+
+        let items: Vec<ListItem> = popup.matches[first..first + rows]
+            .iter()
+            .enumerate()
+            .map(|(i, nick)| {
+                let style = if popup.active && first + i == popup.index {
+                    Style::default().fg(color_for_user(nick)).bg(theme.highlight_bg).bold()
+                } else {
+                    Style::default().fg(color_for_user(nick))
+                };
+                ListItem::new(Span::styled(format!(" {} ", nick), style))
+            })
+            .collect();
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            List::new(items).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+                    .border_type(BorderType::Rounded),
+            ),
+            area,
+        );
+    }
+
     // ── Cursor positioning ───────────────────────────────────────
     match vim_mode {
         VimMode::Insert | VimMode::Normal | VimMode::Visual | VimMode::Vimless => {
             frame.set_cursor_position((
-                input_chunks[1].x + 1 + msg_cursor_pos as u16,
-                input_chunks[1].y,
+                input_chunks[1].x + 1 + nick_width + msg_cursor_col as u16,
+                input_chunks[1].y + (msg_cursor_row - input_scroll) as u16,
             ));
         }
         VimMode::Messages => {
-            let y = msg_index.saturating_sub(msg_scroll) as u16;
+            // Wrapped lines can still run past the bottom, keep the cursor inside the border
+            let y = (msg_row as u16).min(msg_pane.height.saturating_sub(3));
             frame.set_cursor_position((
-                main_chunks[1].x + 1,
-                main_chunks[1].y + 1 + y,
+                msg_pane.x + 1,
+                msg_pane.y + 1 + y,
             ));
         }
         VimMode::Server => {
             if let Some(item) = server_tree.get(server_tree_index) {
                 let x = match item {
                     ServerTreeItem::Server { .. } => 1,
-                    ServerTreeItem::Channel { .. } => 4,
+                    ServerTreeItem::Channel { server_idx, channel_idx } => {
+                        if app.servers[*server_idx].channels[*channel_idx].is_dm { 7 } else { 4 }
+                    }
+                    ServerTreeItem::DmHeader { .. } => 4,
                 };
                 frame.set_cursor_position((
                     main_chunks[0].x + x,
@@ -290,6 +574,7 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     // ── Command popup ────────────────────────────────────────────
     if vim_mode == VimMode::Command {
         let area = centered_rect(50, 75, frame.area());
+        app.cmd_popup_area = area;
         frame.render_widget(Clear, area);
 
         frame.render_widget(
@@ -298,6 +583,7 @@ pub fn render(frame: &mut Frame, app: &mut App) {
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
+                        .border_style(Style::default().fg(theme.border))
                         .border_type(BorderType::Rounded)
                         .title("Command"),
                 ),
@@ -310,6 +596,104 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         ));
     }
 
+    // ── Help popup ───────────────────────────────────────────────
+    if vim_mode == VimMode::Help {
+        let area = popup_rect(70, 80, frame.area());
+        frame.render_widget(Clear, area);
+
+        let mut lines = Vec::new();
+        for (section, entries) in HELP_SECTIONS {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(Span::styled(*section, Style::default().fg(Color::Yellow).bold())));
+            for (keys, desc) in *entries {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<38}", keys), Style::default().fg(Color::Cyan)),
+                    Span::raw(*desc),
+                ]));
+            }
+        }
+
+        // Clamp the scroll so the last line stays at the bottom of the popup
+        let inner_height = area.height.saturating_sub(2) as usize;
+        app.help_scroll = app.help_scroll.min(lines.len().saturating_sub(inner_height));
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .scroll((app.help_scroll as u16, 0))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(theme.border))
+                        .border_type(BorderType::Rounded)
+                        .title("Help (j/k to scroll, Esc to close)"),
+                ),
+            area,
+        );
+    }
+
+    // ── Channel list popup ───────────────────────────────────────
+    if vim_mode == VimMode::ChannelList {
+        let area = popup_rect(70, 80, frame.area());
+        frame.render_widget(Clear, area);
+
+        let filtered = app.filtered_channel_list();
+        let total = filtered.len();
+        let selected = app.channel_list_index.min(total.saturating_sub(1));
+
+        // Row 0 of the inner area holds the filter, the rest is the list
+        let visible = area.height.saturating_sub(3) as usize;
+        let mut scroll = app.channel_list_scroll;
+        if selected < scroll {
+            scroll = selected;
+        } else if visible > 0 && selected >= scroll + visible {
+            scroll = selected + 1 - visible;
+        }
+
+        // Only build lines for what fits on screen, the list can be huge
+        let mut lines = vec![Line::from(vec![
+            Span::styled("Filter: ", Style::default().fg(Color::Yellow).bold()),
+            Span::raw(app.channel_list_filter.clone()),
+        ])];
+        for (i, entry) in filtered.iter().enumerate().skip(scroll).take(visible) {
+            let style = if i == selected {
+                Style::default().bg(theme.highlight_bg).bold()
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<24}", entry.name), style.fg(Color::Cyan)),
+                Span::styled(format!("{:>6} ", entry.users), style.fg(Color::Yellow)),
+                Span::styled(entry.topic.clone(), style),
+            ]));
+        }
+
+        let title = format!(
+            "Channels {}/{}{} (Enter to join, Esc to close)",
+            total,
+            app.channel_list.len(),
+            if app.channel_list_done { "" } else { " loading..." },
+        );
+        let filter_len = app.channel_list_filter.chars().count() as u16;
+
+        app.channel_list_index = selected;
+        app.channel_list_scroll = scroll;
+
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+                    .border_type(BorderType::Rounded)
+                    .title(title),
+            ),
+            area,
+        );
+
+        frame.set_cursor_position((area.x + 1 + "Filter: ".len() as u16 + filter_len, area.y + 1));
+    }
+
     // ── Normal-mode hint ─────────────────────────────────────────
     if vim_mode == VimMode::Normal && !app.norm.is_empty() {
         let hint_area = right_rect(20, 20, frame.area());
@@ -317,10 +701,12 @@ pub fn render(frame: &mut Frame, app: &mut App) {
 
         let block = Block::default()
             .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border))
             .border_type(BorderType::Rounded)
             .title(match app.get_norm_text().as_str() {
                 "d" => "Delete",
                 "g" => "Goto",
+                "\"" | "\"+" => "Register",
                 _ => "",
             });
 
@@ -337,12 +723,115 @@ pub fn render(frame: &mut Frame, app: &mut App) {
             inner,
         );
     }
+
+    // ── Quit confirmation ────────────────────────────────────────
+    if app.quit_confirm {
+        let area = centered_rect(40, 75, frame.area());
+        frame.render_widget(Clear, area);
+
+        frame.render_widget(
+            Paragraph::new("Discard unsent message? y/n")
+                .alignment(Alignment::Center)
+                .style(Style::default().bold())
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(theme.modes.popup))
+                        .border_type(BorderType::Rounded)
+                        .title("Quit"),
+                ),
+            area,
+        );
+    }
 }
 
 // ────────────────────────────────────────────────────────────────
 // Helpers
 // ────────────────────────────────────────────────────────────────
 
+// "just now", "3m ago", "2h ago", "5d ago"
+pub fn format_relative_time(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    let secs = (now - time).num_seconds().max(0);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+// Message rows visible for a terminal of this height (input bar, tab bar and borders excluded)
+pub fn message_viewport_height(terminal_height: u16, input_rows: usize, tab_bar: bool) -> usize {
+    terminal_height
+        .saturating_sub(3)
+        .saturating_sub(2 + input_rows as u16)
+        .saturating_sub(tab_bar as u16) as usize
+}
+
+// One " #chan +3 ● " tab per channel joined on the connected server, tabs that don't fit are left off
+fn render_channel_tabs(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.theme;
+    let mut spans = Vec::new();
+    let mut tabs = Vec::new();
+    let mut x = area.x;
+
+    // A buffer left over from another server is shown first, greyed out and not clickable
+    if let Some(current) = &app.current_channel
+        && !app.servers.iter().any(|s| s.name == current.server_name && s.is_connected)
+    {
+        let label = format!(" {}/{} ", current.server_name, current.channel_name);
+        let width = (label.chars().count() as u16).min(area.width);
+        spans.push(Span::styled(label, Style::default().fg(theme.muted).italic()));
+        spans.push(Span::styled("│", Style::default().fg(theme.border)));
+        x += width + 1;
+    }
+    for ctx in app.joined_channels() {
+        let Some(channel) = app.servers
+            .iter()
+            .filter(|s| s.name == ctx.server_name)
+            .flat_map(|s| s.channels.iter())
+            .find(|c| c.name == ctx.channel_name)
+        else {
+            continue;
+        };
+
+        let mut label = vec![Span::raw(format!(" {}", channel.name))];
+        if channel.unread_count > 0 {
+            label.push(Span::styled(format!(" +{}", channel.unread_count), Style::default().bold()));
+        }
+        if channel.highlighted {
+            label.push(Span::styled(" ●", Style::default().fg(theme.mention).bold()));
+        }
+        label.push(Span::raw(" "));
+
+        let width: u16 = label.iter().map(|s| s.width() as u16).sum();
+        if x + width > area.right() {
+            break;
+        }
+
+        let active = app.is_current_channel(&ctx.server_name, &ctx.channel_name);
+        let style = if active {
+            Style::default().bg(theme.highlight_bg).bold()
+        } else {
+            Style::default().fg(theme.muted)
+        };
+        spans.extend(label.into_iter().map(|s| {
+            let patched = style.patch(s.style);
+            s.style(patched)
+        }));
+        tabs.push(TabSpan { y: area.y, x_start: x, x_end: x + width, ctx });
+        x += width;
+
+        if x < area.right() {
+            spans.push(Span::styled("│", Style::default().fg(theme.border)));
+            x += 1;
+        }
+    }
+
+    frame.render_widget(Line::from(spans), area);
+    app.channel_tabs = tabs;
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     let v = Layout::vertical([
         Constraint::Percentage((100 - percent_y) / 2),
@@ -357,6 +846,20 @@ fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     ]).split(v[1])[1]
 }
 
+fn popup_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let v = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ]).split(area);
+
+    Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ]).split(v[1])[1]
+}
+
 fn right_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     let v = Layout::vertical([
         Constraint::Percentage(100 - percent_y),
@@ -373,38 +876,58 @@ fn right_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
 fn create_tree_view(app: &App) -> Vec<ListItem<'_>> {
     let mut items = Vec::new();
 
-    for row in &app.server_tree {
+    for (row_idx, row) in app.server_tree.iter().enumerate() {
+        // Whether the next row continues this one's branch, for ├── vs ╰──
+        let next = app.server_tree.get(row_idx + 1).copied();
         match *row {
             ServerTreeItem::Server { server_idx } => {
                 let server = &app.servers[server_idx];
                 let status = if server.is_connected { "✓" } else { "✗" };
                 let style = Style::default().fg(Color::White).bold();
 
-                items.push(ListItem::new(Line::from(vec![
+                let mut spans = vec![
                     Span::styled(&server.name, style),
                     Span::styled(format!(" [{}]", status), if server.is_connected { Color::Green } else { Color::Red }),
-                ])));
+                ];
+                // Mentions in a collapsed server still need to be visible
+                if !server.is_expanded && server.channels.iter().any(|c| c.highlighted) {
+                    spans.push(Span::styled(" ●", Style::default().fg(app.theme.mention).bold()));
+                }
+                // Same for unread DMs, they would otherwise wait unseen in the collapsed section
+                let dm_unread: usize = server.channels.iter().filter(|c| c.is_dm).map(|c| c.unread_count).sum();
+                if !server.is_expanded && dm_unread > 0 {
+                    spans.push(Span::styled(format!(" @{}", dm_unread), Style::default().fg(Color::LightMagenta).bold()));
+                }
+
+                items.push(ListItem::new(Line::from(spans)));
             }
             ServerTreeItem::Channel { server_idx, channel_idx } => {
                 let server = &app.servers[server_idx];
                 let channel = &server.channels[channel_idx];
 
-                // Prefix ─ like ├── or ╰──
-                let prefix = if channel_idx + 1 == server.channels.len() { "╰──" } else { "├──" };
+                // DMs hang one level deeper, under the "Direct Messages" row
+                let has_sibling = match next {
+                    Some(ServerTreeItem::Channel { server_idx: s, channel_idx: c }) => {
+                        s == server_idx && server.channels[c].is_dm == channel.is_dm
+                    }
+                    Some(ServerTreeItem::DmHeader { server_idx: s }) => s == server_idx && !channel.is_dm,
+                    _ => false,
+                };
+                let prefix = match (channel.is_dm, has_sibling) {
+                    (false, true) => "├──",
+                    (false, false) => "╰──",
+                    (true, true) => "   ├──",
+                    (true, false) => "   ╰──",
+                };
                 let style = if channel.is_joined {
                     Style::default().fg(Color::LightBlue)
                 } else {
-                    Style::default().fg(Color::DarkGray)
+                    Style::default().fg(app.theme.muted)
                 };
 
-                let channel_name = if channel.is_dm {
-                    format!("@{}", channel.name)
-                } else {
-                    channel.name.clone()
-                };
                 let mut spans = vec![
-                    Span::styled(prefix, Style::default().fg(Color::DarkGray)),
-                    Span::styled(channel_name, style),
+                    Span::styled(prefix, Style::default().fg(app.theme.muted)),
+                    Span::styled(&channel.name, style),
                 ];
 
                 // Show user count if available
@@ -415,6 +938,33 @@ fn create_tree_view(app: &App) -> Vec<ListItem<'_>> {
                     ));
                 }
 
+                if channel.unread_count > 0 {
+                    spans.push(Span::styled(format!(" +{}", channel.unread_count), Style::default().bold()));
+                }
+
+                if channel.highlighted {
+                    spans.push(Span::styled(" ●", Style::default().fg(app.theme.mention).bold()));
+                }
+
+                items.push(ListItem::new(Line::from(spans)));
+            }
+            ServerTreeItem::DmHeader { server_idx } => {
+                let server = &app.servers[server_idx];
+                let dms = || server.channels.iter().filter(|c| c.is_dm);
+                let mut spans = vec![
+                    Span::styled("╰──", Style::default().fg(app.theme.muted)),
+                    Span::styled("Direct Messages", Style::default().fg(Color::LightMagenta)),
+                ];
+
+                // Counted apart from channels so a private message isn't lost among them
+                let unread: usize = dms().map(|c| c.unread_count).sum();
+                if unread > 0 {
+                    spans.push(Span::styled(format!(" +{}", unread), Style::default().bold()));
+                }
+                if dms().any(|c| c.highlighted) {
+                    spans.push(Span::styled(" ●", Style::default().fg(app.theme.mention).bold()));
+                }
+
                 items.push(ListItem::new(Line::from(spans)));
             }
         }
@@ -423,6 +973,131 @@ fn create_tree_view(app: &App) -> Vec<ListItem<'_>> {
     items
 }
 
+// Split "nick: text" or "nick, text" into the nick and the rest when nick is in the channel
+fn leading_mention<'a>(text: &'a str, clients: &[ClientInfo]) -> Option<(&'a str, &'a str)> {
+    let end = text.find([':', ','])?;
+    let nick = &text[..end];
+    clients
+        .iter()
+        .any(|c| c.name.eq_ignore_ascii_case(nick))
+        .then(|| text.split_at(end))
+}
+
+// Quoted parent messages above replies are cut to this many characters
+const REPLY_QUOTE_WIDTH: usize = 80;
+
+// Label of the line between messages from different days
+const DAY_SEPARATOR_FORMAT: &str = "%A, %b %-d";
+
+// Nicks shown at once in the completion popup, it scrolls past that
+const NICK_POPUP_ROWS: usize = 8;
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+// "<nick>", padded on the left to width when aligning, long nicks end in …
+fn aligned_nick(nick: &str, width: usize) -> String {
+    if width == 0 {
+        return format!("<{}>", nick);
+    }
+    let nick = if nick.chars().count() > width {
+        let mut cut: String = nick.chars().take(width - 1).collect();
+        cut.push('…');
+        cut
+    } else {
+        nick.to_string()
+    };
+    format!("{:>1$}", format!("<{}>", nick), width + 2)
+}
+
+// Split message text into spans, styling any URLs so they stand out
+fn text_spans(text: &str, url_color: Color) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, end) in find_urls(text) {
+        if start > last {
+            spans.push(Span::raw(&text[last..start]));
+        }
+        spans.push(Span::styled(
+            &text[start..end],
+            Style::default().fg(url_color).underlined(),
+        ));
+        last = end;
+    }
+    if last < text.len() || spans.is_empty() {
+        spans.push(Span::raw(&text[last..]));
+    }
+    spans
+}
+
+// Byte ranges of every http(s):// URL in the text
+pub fn find_urls(text: &str) -> Vec<(usize, usize)> {
+    let mut urls = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = text[offset..].find("http") {
+        let start = offset + found;
+        let rest = &text[start..];
+        let scheme_len = if rest.starts_with("https://") {
+            8
+        } else if rest.starts_with("http://") {
+            7
+        } else {
+            offset = start + 4;
+            continue;
+        };
+
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+            .unwrap_or(rest.len());
+        // Trailing punctuation usually belongs to the sentence, not the URL
+        let url = rest[..len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'']);
+        if url.len() > scheme_len {
+            urls.push((start, start + url.len()));
+        }
+        offset = start + len.max(scheme_len);
+    }
+    urls
+}
+
+// Map URLs in the rendered message rows to their screen cells
+fn find_url_spans(rows: &[String], area: Rect) -> Vec<UrlSpan> {
+    let mut spans = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        for (start, end) in find_urls(row) {
+            let x_start = row[..start].chars().count() as u16;
+            let x_end = x_start + row[start..end].chars().count() as u16;
+            let mut url = row[start..end].to_string();
+            let mut pieces = vec![(i, x_start, x_end)];
+
+            // A URL that reaches the edge was wrapped onto the following rows
+            let mut next = i + 1;
+            let mut reaches_edge = x_end == area.width;
+            while reaches_edge && let Some(next_row) = rows.get(next) {
+                let rest: String = next_row.chars().take_while(|c| !c.is_whitespace()).collect();
+                if rest.is_empty() {
+                    break;
+                }
+                let width = rest.chars().count() as u16;
+                pieces.push((next, 0, width));
+                url.push_str(&rest);
+                reaches_edge = width == area.width;
+                next += 1;
+            }
+
+            for (row_idx, from, to) in pieces {
+                spans.push(UrlSpan {
+                    y: area.y + row_idx as u16,
+                    x_start: area.x + from,
+                    x_end: area.x + to,
+                    url: url.clone(),
+                });
+            }
+        }
+    }
+    spans
+}
+
 pub fn color_for_user(nick: &str) -> Color {
     let colors = [
         Color::Red, Color::Green, Color::Yellow, Color::Blue,